@@ -0,0 +1,57 @@
+use crate::tokenizer::{TokenInfo, Token, Position};
+use crate::eval;
+use std::collections::HashMap;
+
+/// A while loop whose condition is made of literals only, so it either never ends or
+/// never runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstantCondition {
+    /// Where the condition starts.
+    pub position: Position,
+    pub value: i64
+}
+
+impl ConstantCondition {
+    pub fn always_true(&self) -> bool {
+        self.value != 0
+    }
+}
+
+impl std::fmt::Display for ConstantCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = if self.always_true() { "always true, so the loop never ends" } else { "never true, so the loop body never runs" };
+        write!(f, "Warning: while condition on line {} is {} (it is always {})", self.position.row, verdict, self.value)
+    }
+}
+
+/// Finds while loops in a token stream that passed `parser::parse` whose condition has only
+/// numbers, operators and parentheses in it. Any identifier, even a function call, leaves
+/// the loop alone, as does a condition that can't be computed, e.g. one dividing by zero.
+pub fn constant_while_conditions(tokens: &[TokenInfo]) -> Vec<ConstantCondition> {
+    let mut found = Vec::new();
+    for (i, token_info) in tokens.iter().enumerate() {
+        if token_info.token != Token::While {
+            continue;
+        }
+
+        let condition: Vec<TokenInfo> = tokens[i + 1..].iter()
+            .take_while(|token_info| !matches!(token_info.token, Token::Begin | Token::LeftBraces))
+            .cloned()
+            .collect();
+        let constant = condition.iter().all(|token_info| matches!(token_info.token, Token::Int | Token::Hex |
+            Token::LeftParantheses | Token::RightParantheses) || token_info.token.is_binary_operator());
+        if condition.is_empty() || !constant {
+            continue;
+        }
+
+        let position = condition[0].start_position;
+        let end = tokens[i + 1 + condition.len()].start_position;
+        let mut program = condition;
+        program.push(TokenInfo { token: Token::EOF, lexeme: String::new(), start_position: end, leading_trivia: String::new() });
+        if let Ok(value) = eval::parse(&program, &mut HashMap::new()) {
+            found.push(ConstantCondition { position, value });
+        }
+    }
+
+    found
+}
@@ -0,0 +1,48 @@
+use crate::tokenizer::{Tokens, Token};
+
+const CONTEXT_TOKENS: usize = 5;
+const CONTEXT_CHARS: usize = 40;
+
+/// Describes what came right before `tokens[end]` in the same statement for error messages,
+/// e.g. `"x := 5 +"`. Longer fragments keep their end and start with `...`; with nothing
+/// before it gives `start of input` or `start of statement`.
+pub(crate) fn error_context<S: Tokens + ?Sized>(tokens: &S, end: usize) -> String {
+    let window = end.saturating_sub(CONTEXT_TOKENS);
+    let start = (window..end).rev()
+        .find(|&i| tokens.token(i) == Token::Semicolon)
+        .map_or(window, |semicolon| semicolon + 1);
+    let fragment = (start..end)
+        .map(|i| tokens.lexeme(i))
+        .filter(|lexeme| !lexeme.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    if fragment.is_empty() {
+        return String::from(if start == 0 { "start of input" } else { "start of statement" });
+    }
+
+    let len = fragment.chars().count();
+    if len > CONTEXT_CHARS {
+        let tail: String = fragment.chars().skip(len - CONTEXT_CHARS).collect();
+        format!("\"...{}\"", tail)
+    } else {
+        format!("\"{}\"", fragment)
+    }
+}
+
+/// Whether `tokens[i]` is a binary operator right after another one, as in `4 ** 2`. A `+` or
+/// `-` there is a sign (`5 * -3`, `5 - -3`) unless it repeats the operator with nothing in
+/// between, as in `5 ++ 3`.
+pub(crate) fn doubled_operator<S: Tokens + ?Sized>(tokens: &S, i: usize) -> bool {
+    let Some(previous) = i.checked_sub(1).filter(|_| i < tokens.len()) else {
+        return false;
+    };
+    let (first, second) = (tokens.token(previous), tokens.token(i));
+    if !first.is_binary_operator() || !second.is_binary_operator() {
+        return false;
+    }
+
+    let (first_end, second_start) = (tokens.end_position(previous), tokens.start_position(i));
+    let adjacent = first_end.row == second_start.row && first_end.col + 1 == second_start.col;
+    !matches!(second, Token::Addition | Token::Subtraction) || (second == first && adjacent)
+}
@@ -0,0 +1,83 @@
+use crate::tokenizer::Position;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub position: Position,
+    pub message: String
+}
+
+/// Problems reported by every phase of a run, collected so they can be printed as one report.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn error(&mut self, position: Position, message: String) {
+        self.push(Diagnostic { severity: Severity::Error, position, message });
+    }
+
+    pub fn warning(&mut self, position: Position, message: String) {
+        self.push(Diagnostic { severity: Severity::Warning, position, message });
+    }
+
+    /// Orders by position and keeps only the first diagnostic of each severity at a position,
+    /// so a bad character is not reported again by the parser tripping over it.
+    pub fn sort_by_position(&mut self) {
+        self.diagnostics.sort_by_key(|diagnostic| (diagnostic.position.row, diagnostic.position.col));
+        self.diagnostics.dedup_by(|later, earlier| later.position == earlier.position && later.severity == earlier.severity);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Renders every diagnostic followed by its source line and a caret under the column.
+    pub fn render(&self, source: &str) -> String {
+        let lines: Vec<&str> = source.lines().collect();
+        let mut rendered = String::new();
+        for diagnostic in &self.diagnostics {
+            let Position { row, col } = diagnostic.position;
+            rendered.push_str(&format!("{} at {}:{}: {}\n", diagnostic.severity, row, col, diagnostic.message));
+            if let Some(line) = lines.get(row as usize - 1) {
+                let gutter = row.to_string().len();
+                rendered.push_str(&format!("{} | {}\n", row, line));
+                rendered.push_str(&format!("{} | {}^\n", " ".repeat(gutter), " ".repeat(col as usize - 1)));
+            }
+        }
+
+        rendered
+    }
+}
@@ -1,5 +1,9 @@
-use crate::tokenizer::{TokenInfo, Token, Position};
-use std::collections::HashMap;
+use crate::tokenizer::{TokenInfo, Token, Tokens, CompactTokens, Position, Strictness, RESERVED_WORDS, read_quoted, write_quoted};
+use crate::parser::{ParsedProgram, DEFAULT_MAX_DEPTH};
+use crate::context::{error_context, doubled_operator};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use crate::literal::{group_digits, parse_literal, LiteralError};
 
 #[derive(Debug)]
 pub enum Error {
@@ -11,7 +15,26 @@ pub enum Error {
     ExpectedStartingBrackets(TokenInfo),
     ExpectedStartingParantheses(TokenInfo),
     MissingSemicolon(TokenInfo),
-    UndefinedVariable(TokenInfo)
+    UndefinedVariable(TokenInfo),
+    IntegerOverflow(TokenInfo),
+    UnusedValue(TokenInfo),
+    InvalidNumber(TokenInfo),
+    ArithmeticOverflow(TokenInfo),
+    DivisionByZero(TokenInfo),
+    StepLimitExceeded(TokenInfo),
+    UnknownFunction(TokenInfo),
+    ReservedWord(TokenInfo),
+    /// The closer that was found, and the opener of the block it doesn't match.
+    MismatchedBlock(TokenInfo, Box<TokenInfo>),
+    /// Two binary operators in a row: the first and the one after it.
+    DoubledOperator(TokenInfo, Box<TokenInfo>),
+    /// A statement starting with an operator that can't be a sign.
+    ExpressionExpected(TokenInfo),
+    StatementLimitExceeded(TokenInfo),
+    ExpressionTooDeep(TokenInfo),
+    TooManyVariables(TokenInfo),
+    ArgumentCount(TokenInfo, usize),
+    InvalidArgument(TokenInfo, String)
 }
 
 impl std::error::Error for Error {}
@@ -20,38 +43,305 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Generic(token_info, string) =>
-                write!(f, "Syntax error: unexpected token '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token.to_string(), string, token_info.start_position.row),
+                write!(f, "Syntax error: unexpected token '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token, string, token_info.span()),
             Error::InvalidFor(token_info) =>
-                write!(f, "Syntax error: invalid for loop structure, unexpected token '{}' of type {} on line {}", token_info.lexeme, token_info.token.to_string(), token_info.start_position.row),
+                write!(f, "Syntax error: invalid for loop structure, unexpected token '{}' of type {} on line {}", token_info.lexeme, token_info.token, token_info.span()),
             Error::InvalidAssignment(token_info, string) =>
-                write!(f, "Syntax error: invalid assignment; found '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token.to_string(), string, token_info.start_position.row),
+                write!(f, "Syntax error: invalid assignment; found '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token, string, token_info.span()),
             Error::MissingClosingBrackets(token_info) =>
-                write!(f, "Syntax error: missing closing brackets on line {}", token_info.start_position.row),
+                write!(f, "Syntax error: missing closing brackets on line {}", token_info.span()),
             Error::MissingClosingParantheses(token_info) =>
-                write!(f, "Syntax error: missing closing parantheses on line {}", token_info.start_position.row),
+                write!(f, "Syntax error: missing closing parantheses on line {}", token_info.span()),
             Error::ExpectedStartingBrackets(token_info) =>
-                write!(f, "Syntax error: expected {{, found '{}' on line {}", token_info.lexeme, token_info.start_position.row),
+                write!(f, "Syntax error: expected {{, found '{}' on line {}", token_info.lexeme, token_info.span()),
             Error::ExpectedStartingParantheses(token_info) =>
-                write!(f, "Syntax error: expected (, found '{}' on line {}", token_info.lexeme, token_info.start_position.row),
+                write!(f, "Syntax error: expected (, found '{}' on line {}", token_info.lexeme, token_info.span()),
             Error::MissingSemicolon(token_info) =>
-                write!(f, "Syntax error: missing semicolon ';' on line {}", token_info.start_position.row),
+                write!(f, "Syntax error: missing semicolon ';' on line {}", token_info.span()),
             Error::UndefinedVariable(token_info) =>
-                write!(f, "Evaluation error: variable '{}' on line {} undefined", token_info.lexeme, token_info.start_position.row)
+                write!(f, "Evaluation error: variable '{}' on line {} undefined", token_info.lexeme, token_info.span()),
+            Error::IntegerOverflow(token_info) =>
+                write!(f, "Evaluation error: integer literal '{}' on line {} is out of range", token_info.lexeme, token_info.span()),
+            Error::UnusedValue(token_info) =>
+                write!(f, "Evaluation error: value of the statement starting with '{}' on line {} is unused; assign it or pass it to CONSOLE", token_info.lexeme, token_info.span()),
+            Error::InvalidNumber(token_info) =>
+                write!(f, "Evaluation error: malformed number literal '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::ArithmeticOverflow(token_info) =>
+                write!(f, "Evaluation error: arithmetic overflow in '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::DivisionByZero(token_info) =>
+                write!(f, "Evaluation error: division by zero on line {}", token_info.span()),
+            Error::StepLimitExceeded(token_info) =>
+                write!(f, "Evaluation error: step limit exceeded at '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::UnknownFunction(token_info) =>
+                write!(f, "Evaluation error: unknown function '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::MismatchedBlock(token_info, opener) =>
+                write!(f, "Evaluation error: found '{}' on line {} but block was opened with '{}' on line {}", token_info.lexeme, token_info.span(), opener.lexeme, opener.span()),
+            Error::ExpressionExpected(token_info) =>
+                write!(f, "Evaluation error: expected a value or '(' at the start of a statement, found '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::DoubledOperator(token_info, second) =>
+                write!(f, "Evaluation error: operator '{}' on line {} is followed by another operator '{}' on line {}", token_info.lexeme, token_info.span(), second.lexeme, second.span()),
+            Error::ReservedWord(token_info) =>
+                write!(f, "Evaluation error: '{}' is a reserved word and cannot be used as a variable name on line {}; reserved words are {}", token_info.lexeme, token_info.span(), RESERVED_WORDS.map(|(word, _)| word).join(", ")),
+            Error::StatementLimitExceeded(token_info) =>
+                write!(f, "Evaluation error: statement limit exceeded at '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::ExpressionTooDeep(token_info) =>
+                write!(f, "Evaluation error: expression nested too deeply at '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::TooManyVariables(token_info) =>
+                write!(f, "Evaluation error: too many variables, cannot create '{}' on line {}", token_info.lexeme, token_info.span()),
+            Error::ArgumentCount(token_info, expected) =>
+                write!(f, "Evaluation error: function '{}' expects {} argument(s) on line {}", token_info.lexeme, expected, token_info.span()),
+            Error::InvalidArgument(token_info, string) =>
+                write!(f, "Evaluation error: invalid argument to '{}' on line {}: {}", token_info.lexeme, token_info.span(), string)
         }
     }
 }
 
-struct ParserInfo<'slice> {
-    tokens: &'slice [TokenInfo],
-    current_token_info: TokenInfo,
+impl Error {
+    pub fn token_info(&self) -> &TokenInfo {
+        match self {
+            Error::Generic(token_info, _) | Error::InvalidAssignment(token_info, _) => token_info,
+            Error::InvalidFor(token_info) | Error::MissingClosingBrackets(token_info) |
+            Error::MissingClosingParantheses(token_info) | Error::ExpectedStartingBrackets(token_info) |
+            Error::ExpectedStartingParantheses(token_info) | Error::MissingSemicolon(token_info) |
+            Error::UndefinedVariable(token_info) | Error::IntegerOverflow(token_info) |
+            Error::UnusedValue(token_info) | Error::InvalidNumber(token_info) |
+            Error::ArithmeticOverflow(token_info) | Error::DivisionByZero(token_info) |
+            Error::StepLimitExceeded(token_info) | Error::UnknownFunction(token_info) |
+            Error::ReservedWord(token_info) | Error::StatementLimitExceeded(token_info) |
+            Error::MismatchedBlock(token_info, _) | Error::DoubledOperator(token_info, _) | Error::ExpressionExpected(token_info) |
+            Error::ExpressionTooDeep(token_info) | Error::TooManyVariables(token_info) |
+            Error::ArgumentCount(token_info, _) |
+            Error::InvalidArgument(token_info, _) => token_info
+        }
+    }
+}
+
+/// When the `to` bound of a for loop is computed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ForBoundMode {
+    /// Computed once before the first iteration; assignments in the body don't affect it.
+    #[default]
+    Frozen,
+    /// Computed again before every iteration, like the condition of a while loop, so
+    /// `n := 3; for (i := 1 to n) begin n := 5 end` runs five times instead of three.
+    Reevaluated
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Options {
+    /// Reject top-level statements whose value is neither assigned nor printed,
+    /// instead of silently adding it to the program result.
+    pub reject_unused_values: bool,
+    /// Print CONSOLE output with `_` between groups of three digits, e.g. `1_000_000`.
+    pub group_digits: bool,
+    /// Stop with `Error::StepLimitExceeded` after this many statements and loop iterations.
+    pub max_steps: Option<u64>,
+    pub for_bound: ForBoundMode,
+    pub limits: EvalLimits,
+    /// Same as `parser::Options::permissive_console`.
+    pub permissive_console: bool,
+    /// `Strict` ignores `permissive_console`, turns on `reject_unused_values`, rejects
+    /// assignments inside expressions and keeps for loop variables local to their loop; see
+    /// `tokenizer::Strictness`.
+    pub strictness: Strictness,
+    /// Record which tokens were evaluated, for `RunOutput::covered_lines` and
+    /// `uncovered_lines`, which are both empty otherwise.
+    pub coverage: bool
+}
+
+impl Options {
+    fn effective(mut self) -> Self {
+        if self.strictness == Strictness::Strict {
+            self.permissive_console = false;
+            self.reject_unused_values = true;
+        }
+        self
+    }
+}
+
+/// Resource limits for untrusted programs; `None` means unlimited.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct EvalLimits {
+    /// Statements executed, counting every run of a statement in a loop body. Unlike
+    /// `Options::max_steps`, loop iterations themselves are not counted.
+    pub max_statements: Option<u64>,
+    /// Nesting of expressions, e.g. parentheses and right-hand sides of assignments. Counted
+    /// the same way as `parser::Options::max_depth`, and `None` means its default rather than
+    /// unlimited, so tokens that skipped the parser can't overflow the stack either.
+    pub max_expr_depth: Option<u32>,
+    /// Variables in the map, including ones that were there before the run.
+    pub max_variables: Option<usize>
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum VariableKind {
+    /// Already in the variable map when the run started, e.g. seeded by main.
+    Seeded,
+    LoopControl,
+    Assigned
+}
+
+#[derive(Debug, Clone)]
+struct Access {
+    kind: VariableKind,
+    read: bool,
+    last_write: Position,
+    /// Writes nothing has read yet, at most one per block run that is still going, with the
+    /// block run they happened in.
+    unread_writes: Vec<(Position, usize)>
+}
+
+/// A variable assigned twice in the same run of a block with no read in between, so the
+/// first value is lost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateAssignment {
+    pub name: String,
+    pub first: Position,
+    pub second: Position
+}
+
+impl std::fmt::Display for DuplicateAssignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Warning: '{}' assigned on line {} is assigned again on line {} without being read", self.name, self.first.row, self.second.row)
+    }
+}
+
+/// What a successful run produced besides changing the variable map.
+#[derive(Debug)]
+pub struct RunOutput {
+    pub value: i64,
+    accesses: HashMap<String, Access>,
+    duplicates: Vec<DuplicateAssignment>,
+    code_lines: BTreeSet<u32>,
+    covered_lines: BTreeSet<u32>
+}
+
+impl RunOutput {
+    /// Lines with at least one token that was evaluated, e.g. not the body of a loop that
+    /// never ran.
+    pub fn covered_lines(&self) -> BTreeSet<u32> {
+        self.covered_lines.clone()
+    }
+
+    /// Lines with code on them that were never evaluated. Blank lines are in neither set.
+    pub fn uncovered_lines(&self) -> BTreeSet<u32> {
+        self.code_lines.difference(&self.covered_lines).copied().collect()
+    }
+
+    /// Variables assigned during the run but never read afterwards, with the position of
+    /// their last write, in source order. Loop control variables and variables that
+    /// existed before the run are left out.
+    pub fn unused_variables(&self) -> Vec<(String, Position)> {
+        let mut unused: Vec<(String, Position)> = self.accesses.iter()
+            .filter(|(_, access)| access.kind == VariableKind::Assigned && !access.read)
+            .map(|(name, access)| (name.clone(), access.last_write))
+            .collect();
+        unused.sort_by_key(|(_, position)| (position.row, position.col));
+        unused
+    }
+
+    /// Assignments whose value was overwritten before anything read it, in the order the
+    /// second assignments ran. Only writes in the same run of a block are compared: a write
+    /// in a loop body may not happen, so it doesn't make one before the loop useless, while
+    /// two in the body are reported once however many times the loop runs.
+    pub fn duplicate_assignments(&self) -> Vec<DuplicateAssignment> {
+        self.duplicates.clone()
+    }
+}
+
+struct ParserInfo<'slice, S: ?Sized = [TokenInfo]> {
+    tokens: &'slice S,
+    current: usize,
     i: usize,
-    variables: &'slice mut HashMap<String, i64>
+    variables: &'slice mut HashMap<String, i64>,
+    options: Options,
+    steps: u64,
+    statements: u64,
+    depth: u32,
+    in_console: bool,
+    /// First token of the innermost statement, whose own assignment Strict allows.
+    statement_start: usize,
+    accesses: HashMap<String, Access>,
+    duplicates: Vec<DuplicateAssignment>,
+    /// The block runs currently going, outermost first: 0 for the top level, and a new number
+    /// every time a block is entered, so each loop iteration gets its own.
+    blocks: Vec<usize>,
+    blocks_entered: usize,
+    /// Where CONSOLE and VARS write to; stdout when `None`.
+    captured: Option<Vec<u8>>,
+    /// Control variables of the for loops currently running, outermost first.
+    loop_variables: Vec<String>,
+    /// Which tokens were matched at least once, for `RunOutput::covered_lines`; empty
+    /// unless `Options::coverage` is set.
+    evaluated: Vec<bool>,
+    finished: bool
 }
 
-impl ParserInfo<'_> {
+impl<'slice, S: Tokens + ?Sized> ParserInfo<'slice, S> {
+    fn new(tokens: &'slice S, variables: &'slice mut HashMap<String, i64>, options: Options) -> Self {
+        ParserInfo {
+            tokens,
+            current: 0,
+            i: 0,
+            variables,
+            options: options.effective(),
+            steps: 0,
+            statements: 0,
+            depth: 0,
+            in_console: false,
+            statement_start: 0,
+            accesses: HashMap::new(),
+            duplicates: Vec::new(),
+            blocks: vec![0],
+            blocks_entered: 0,
+            captured: None,
+            loop_variables: Vec::new(),
+            evaluated: if options.coverage { vec![false; tokens.len()] } else { Vec::new() },
+            finished: false
+        }
+    }
+
+    /// Runs the next top-level statement and returns where it starts and its value, or
+    /// `None` once the end of input is reached.
+    fn run_statement(&mut self) -> Result<Option<(usize, i64)>, Error> {
+        if self.finished || self.match_token(Token::EOF) {
+            self.finished = true;
+            return Ok(None);
+        }
+
+        if self.options.reject_unused_values && !self.statement_has_effect() {
+            return Err(Error::UnusedValue(self.tokens.token_info(self.i)));
+        }
+
+        self.statement()?;
+        let statement = self.i;
+        let value = self.evaluate_statement()?;
+        if self.match_token(Token::EOF) {
+            self.finished = true;
+        } else {
+            self.end_of_statement()?;
+        }
+
+        Ok(Some((statement, value)))
+    }
+
+    fn print(&mut self, text: &str) {
+        match &mut self.captured {
+            Some(captured) => writeln!(captured, "{}", text).expect("writing to a Vec cannot fail"),
+            None => println!("{}", text)
+        }
+    }
+
+    fn current_token_info(&self) -> TokenInfo {
+        self.tokens.token_info(self.current)
+    }
+
     fn match_token(&mut self, expected_token: Token) -> bool {
-        self.current_token_info = self.tokens[self.i].clone();
-        if self.tokens[self.i].token == expected_token {
+        self.current = self.i;
+        if self.tokens.token(self.i) == expected_token {
+            if self.options.coverage {
+                self.evaluated[self.i] = true;
+            }
             self.i += 1;
             return true;
         }
@@ -59,178 +349,740 @@ impl ParserInfo<'_> {
         false
     }
 
-    fn last_n_token_lexemes(&self, n: u32) -> String {
-        let mut counter = 1;
-        let mut string: String = String::from("");
-        while n > 0 {
-            string = format!("{} {}", &string, self.tokens[self.i - counter].lexeme);
-            counter += 1;
+    fn error_context(&self) -> String {
+        error_context(self.tokens, self.i)
+    }
 
-            if self.i - counter == 0 {
-                break;
+    fn lines(&self, only_evaluated: bool) -> BTreeSet<u32> {
+        (0..self.tokens.len())
+            .filter(|&i| self.tokens.token(i) != Token::EOF && (self.evaluated[i] || !only_evaluated))
+            .map(|i| self.tokens.start_position(i).row)
+            .collect()
+    }
+
+    fn step(&mut self) -> Result<(), Error> {
+        self.steps += 1;
+        match self.options.max_steps {
+            Some(max_steps) if self.steps > max_steps =>
+                Err(Error::StepLimitExceeded(self.tokens.token_info(self.i))),
+            _ => Ok(())
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), Error> {
+        self.step()?;
+        self.statements += 1;
+        match self.options.limits.max_statements {
+            Some(max_statements) if self.statements > max_statements =>
+                Err(Error::StatementLimitExceeded(self.tokens.token_info(self.i))),
+            _ => Ok(())
+        }
+    }
+
+    /// Sets the variable named by the token at `var`.
+    fn write_variable(&mut self, var: usize, value: i64, kind: VariableKind) -> Result<(), Error> {
+        let (name, position) = (self.tokens.lexeme(var), self.tokens.start_position(var));
+        // names are only allocated the first time they're written, not on every assignment
+        let access_kind = match self.variables.get_mut(name) {
+            Some(variable) => {
+                *variable = value;
+                VariableKind::Seeded
+            },
+            None => {
+                if self.options.limits.max_variables.is_some_and(|max_variables| self.variables.len() >= max_variables) {
+                    return Err(Error::TooManyVariables(self.tokens.token_info(var)));
+                }
+                self.variables.insert(name.to_string(), value);
+                kind
+            }
+        };
+
+        let access = match self.accesses.get_mut(name) {
+            Some(access) => {
+                access.last_write = position;
+                access
+            },
+            None => self.accesses.entry(name.to_string())
+                .or_insert(Access { kind: access_kind, read: false, last_write: position, unread_writes: Vec::new() })
+        };
+        // for loops write their control variable on every iteration, which isn't a lost value
+        if kind != VariableKind::LoopControl {
+            let block = *self.blocks.last().expect("the top level is never left");
+            access.unread_writes.retain(|(_, unread_block)| self.blocks.contains(unread_block));
+            match access.unread_writes.iter_mut().find(|(_, unread_block)| *unread_block == block) {
+                Some((first, _)) => {
+                    let duplicate = DuplicateAssignment { name: name.to_string(), first: *first, second: position };
+                    *first = position;
+                    if !self.duplicates.contains(&duplicate) {
+                        self.duplicates.push(duplicate);
+                    }
+                },
+                None => access.unread_writes.push((position, block))
             }
         }
+        Ok(())
+    }
 
-        string.chars().rev().collect::<String>()
+    fn read_variable(&mut self, var: usize) -> Result<i64, Error> {
+        let name = self.tokens.lexeme(var);
+        let value = *self.variables.get(name).ok_or_else(|| Error::UndefinedVariable(self.tokens.token_info(var)))?;
+        if let Some(access) = self.accesses.get_mut(name) {
+            access.read = true;
+            access.unread_writes.clear();
+        }
+        Ok(value)
     }
 
     fn evaluate_bitwise(&mut self) -> Result<i64, Error> {
+        let max_expr_depth = self.options.limits.max_expr_depth.unwrap_or(DEFAULT_MAX_DEPTH as u32);
+        if self.depth >= max_expr_depth {
+            return Err(Error::ExpressionTooDeep(self.tokens.token_info(self.i)));
+        }
+
+        self.depth += 1;
         let mut value = self.evaluate_additive()?;
         while self.match_token(Token::BWAnd) || self.match_token(Token::BWOr) {
-            let operator = self.current_token_info.token;
+            let operator = self.tokens.token(self.current);
             let next_value = self.evaluate_additive()?;
             match operator {
-                Token::BWAnd => value = value & next_value,
-                Token::BWOr => value = value | next_value,
-                _ => return Err(Error::Generic(self.current_token_info.clone(), self.last_n_token_lexemes(3))),
+                Token::BWAnd => value &= next_value,
+                Token::BWOr => value |= next_value,
+                _ => return Err(Error::Generic(self.current_token_info(), self.error_context())),
             }
         }
+        self.depth -= 1;
         Ok(value)
     }
 
     fn evaluate_additive(&mut self) -> Result<i64, Error> {
         let mut value = self.evaluate_multiplicative()?;
         while self.match_token(Token::Addition) || self.match_token(Token::Subtraction) {
-            let operator = self.current_token_info.token;
+            let operator = self.current;
             let next_value = self.evaluate_multiplicative()?;
-            match operator {
-                Token::Addition => value = value + next_value,
-                Token::Subtraction => value = value - next_value,
-                _ => return Err(Error::Generic(self.current_token_info.clone(), self.last_n_token_lexemes(3))),
-            }
+            value = match self.tokens.token(operator) {
+                Token::Addition => value.checked_add(next_value),
+                Token::Subtraction => value.checked_sub(next_value),
+                _ => return Err(Error::Generic(self.current_token_info(), self.error_context())),
+            }.ok_or_else(|| Error::ArithmeticOverflow(self.tokens.token_info(operator)))?;
         }
         Ok(value)
     }
 
     fn evaluate_multiplicative(&mut self) -> Result<i64, Error> {
-        let mut value = self.evaluate_unary()?;
+        let mut value = self.evaluate_comparison()?;
         while self.match_token(Token::Multiplication) || self.match_token(Token::Division) {
-            let operator = self.current_token_info.token;
+            let operator = self.current;
+            let next_value = self.evaluate_comparison()?;
+            value = match self.tokens.token(operator) {
+                Token::Multiplication => value.checked_mul(next_value),
+                Token::Division if next_value == 0 => return Err(Error::DivisionByZero(self.tokens.token_info(operator))),
+                Token::Division => value.checked_div(next_value),
+                _ => return Err(Error::Generic(self.current_token_info(), self.error_context())),
+            }.ok_or_else(|| Error::ArithmeticOverflow(self.tokens.token_info(operator)))?;
+        }
+        Ok(value)
+    }
+
+    /// Comparisons bind tighter than `*` and `/`, as in the parser, and yield 1 or 0.
+    fn evaluate_comparison(&mut self) -> Result<i64, Error> {
+        let mut value = self.evaluate_unary()?;
+        while self.match_token(Token::GreaterThan) || self.match_token(Token::LowerThan) || self.match_token(Token::Comparison) {
+            let operator = self.tokens.token(self.current);
             let next_value = self.evaluate_unary()?;
-            match operator {
-                Token::Multiplication => value = value * next_value,
-                Token::Division => value = value / next_value,
-                _ => return Err(Error::Generic(self.current_token_info.clone(), self.last_n_token_lexemes(3))),
-            }
+            value = match operator {
+                Token::GreaterThan => value > next_value,
+                Token::LowerThan => value < next_value,
+                _ => value == next_value
+            } as i64;
         }
         Ok(value)
     }
 
     fn evaluate_unary(&mut self) -> Result<i64, Error> {
+        if doubled_operator(self.tokens, self.i) {
+            let first = self.tokens.token_info(self.i - 1);
+            return Err(Error::DoubledOperator(first, Box::new(self.tokens.token_info(self.i))));
+        }
+
         if self.match_token(Token::Addition) {
             return self.evaluate_primary();
         } else if self.match_token(Token::Subtraction) {
+            let operator = self.current;
             let value = self.evaluate_primary()?;
-            return Ok(-value);
+            return value.checked_neg().ok_or_else(|| Error::ArithmeticOverflow(self.tokens.token_info(operator)));
         }
         self.evaluate_primary()
     }
 
     fn evaluate_primary(&mut self) -> Result<i64, Error> {
-        if self.match_token(Token::Int) {
-            Ok(self.current_token_info.lexeme.parse().unwrap())
-        } else if self.match_token(Token::Hex) {
-            let hex_value = self.current_token_info.lexeme.trim_start_matches("#");
-            Ok(i64::from_str_radix(hex_value, 16).unwrap())
+        if self.tokens.token(self.i).is_reserved_word() && self.tokens.token(self.i + 1) == Token::Assignment {
+            return Err(Error::ReservedWord(self.tokens.token_info(self.i)));
+        }
+
+        if self.match_token(Token::Int) || self.match_token(Token::Hex) {
+            parse_number(self.tokens, self.current)
         } else if self.match_token(Token::Identifier) {
-            let var = self.current_token_info.clone();
+            let var = self.current;
+            // assignment yields the assigned value, so a := b := 5 sets both
             if self.match_token(Token::Assignment) {
+                let nested = var != self.statement_start;
+                if self.in_console || (nested && self.options.strictness == Strictness::Strict) {
+                    return Err(Error::InvalidAssignment(self.current_token_info(), self.error_context()));
+                }
+
                 let value = self.evaluate_bitwise()?;
-                self.variables.insert(var.lexeme, value);
+                self.write_variable(var, value, VariableKind::Assigned)?;
                 Ok(value)
+            } else if self.match_token(Token::LeftParantheses) {
+                self.evaluate_call(var)
             } else {
-                match self.variables.get(&var.lexeme) {
-                    Some(value) => Ok(*value),
-                    None => Err(Error::UndefinedVariable(var)),
-                }
+                self.read_variable(var)
+            }
+        } else if self.options.permissive_console && self.match_token(Token::Console) {
+            self.evaluate_console()
+        } else if self.match_token(Token::Vars) {
+            match &mut self.captured {
+                Some(captured) => dump_vars(self.variables, captured),
+                None => dump_vars(self.variables, &mut std::io::stdout())
+            }.expect("failed printing to stdout");
+            for access in self.accesses.values_mut() {
+                access.read = true;
+                access.unread_writes.clear();
             }
-        } else if self.match_token(Token::Console) {
-            println!("{}", self.evaluate_bitwise()?);
-            return Ok(0);
+            Ok(0)
         } else if self.match_token(Token::LeftParantheses) {
             let value = self.evaluate_bitwise()?;
             if !self.match_token(Token::RightParantheses) {
-                return Err(Error::MissingClosingParantheses(self.current_token_info.clone()));
+                return Err(Error::MissingClosingParantheses(self.current_token_info()));
             }
             Ok(value)
         }
         else if self.match_token(Token::For) {
             self.evaluate_for()
+        } else if self.match_token(Token::While) {
+            self.evaluate_while()
+        } else if self.match_token(Token::LeftBraces) {
+            self.evaluate_block(Token::RightBraces)
         } else {
-            Err(Error::Generic(self.current_token_info.clone(), self.last_n_token_lexemes(3)))
+            Err(Error::Generic(self.current_token_info(), self.error_context()))
+        }
+    }
+
+    fn evaluate_statement(&mut self) -> Result<i64, Error> {
+        let first = self.tokens.token(self.i);
+        if first.is_binary_operator() && !matches!(first, Token::Addition | Token::Subtraction) {
+            return Err(Error::ExpressionExpected(self.tokens.token_info(self.i)));
+        }
+
+        self.statement_start = self.i;
+        if !self.options.permissive_console && self.match_token(Token::Console) {
+            return self.evaluate_console();
+        }
+
+        self.evaluate_bitwise()
+    }
+
+    fn evaluate_console(&mut self) -> Result<i64, Error> {
+        let argument = self.tokens.token(self.i);
+        if argument == Token::Assignment {
+            // CONSOLE := 5
+            return Err(Error::ReservedWord(self.current_token_info()));
+        } else if matches!(argument, Token::In | Token::Begin | Token::End | Token::To) {
+            return Err(Error::ReservedWord(self.tokens.token_info(self.i)));
+        }
+
+        let in_console = std::mem::replace(&mut self.in_console, !self.options.permissive_console);
+        let value = self.evaluate_bitwise()?;
+        self.in_console = in_console;
+        if self.options.group_digits {
+            self.print(&group_digits(value));
+        } else {
+            self.print(&value.to_string());
+        }
+        Ok(0)
+    }
+
+    /// Calls the function named by the token at `function`.
+    fn evaluate_call(&mut self, function: usize) -> Result<i64, Error> {
+        let mut arguments = vec![self.evaluate_bitwise()?];
+        while self.match_token(Token::Comma) {
+            arguments.push(self.evaluate_bitwise()?);
+        }
+        if !self.match_token(Token::RightParantheses) {
+            return Err(Error::MissingClosingParantheses(self.current_token_info()));
+        }
+
+        let name = self.tokens.lexeme(function);
+        let function = self.tokens.token_info(function);
+        match (name, arguments.as_slice()) {
+            ("abs", &[value]) => value.checked_abs().ok_or(Error::ArithmeticOverflow(function)),
+            ("clamp", &[_, lo, hi]) if lo > hi =>
+                Err(Error::InvalidArgument(function, format!("lower bound {} is greater than upper bound {}", lo, hi))),
+            ("clamp", &[value, lo, hi]) => Ok(value.clamp(lo, hi)),
+            ("abs", _) => Err(Error::ArgumentCount(function, 1)),
+            ("clamp", _) => Err(Error::ArgumentCount(function, 3)),
+            _ => Err(Error::UnknownFunction(function))
         }
     }
 
     fn evaluate_for(&mut self) -> Result<i64, Error> {
-        self.match_token(Token::LeftParantheses);
-        self.match_token(Token::Identifier);
-        let var = self.current_token_info.lexeme.clone();
-        self.match_token(Token::Assignment);
+        if !self.match_token(Token::LeftParantheses) {
+            return Err(Error::ExpectedStartingParantheses(self.current_token_info()));
+        }
+
+        if !self.match_token(Token::Identifier) {
+            return Err(Error::InvalidAssignment(self.current_token_info(), self.error_context()));
+        }
+
+        let var = self.current;
+        let name = self.tokens.lexeme(var);
+        if !self.match_token(Token::Assignment) {
+            return Err(Error::InvalidAssignment(self.current_token_info(), self.error_context()));
+        }
 
         let eval = self.evaluate_bitwise()?;
-        self.variables.insert(var.clone(), eval);
-        self.match_token(Token::To);
-        let end_value = self.evaluate_bitwise()?;
-        self.match_token(Token::RightParantheses);
-
-        self.match_token(Token::Begin);
-        {
-            let i = self.i;
-            let mut control_var = *self.variables.get(&var).unwrap();
-            while control_var <= end_value {
-                while !self.match_token(Token::End) {
-                    self.evaluate_bitwise()?;
-                    if self.match_token(Token::End) {
-                        break;
-                    } else {
-                        self.end_of_statement()?;
-                    }
-                }
+        // an inner loop reusing an enclosing loop's variable must not change it for the outer body
+        let shadowed = if self.options.strictness == Strictness::Strict || self.loop_variables.iter().any(|loop_variable| loop_variable == name) {
+            Some(self.variables.get(name).copied())
+        } else {
+            None
+        };
+        self.write_variable(var, eval, VariableKind::LoopControl)?;
+        if !self.match_token(Token::To) {
+            return Err(Error::InvalidFor(self.current_token_info()));
+        }
 
-                if control_var + 1 > end_value {
-                    break;
-                }
+        let bound = self.i;
+        let mut end_value = self.evaluate_bitwise()?;
+        if !self.match_token(Token::RightParantheses) {
+            return Err(Error::MissingClosingParantheses(self.current_token_info()));
+        }
 
-                self.i = i;
+        let closer = match self.block_opener() {
+            Some(closer) => closer,
+            None => return Err(Error::InvalidFor(self.current_token_info()))
+        };
 
-                control_var += 1;
-                self.variables.insert(var.to_string(), control_var);
+        let body = self.i;
+        if eval > end_value {
+            self.skip_block()?;
+        }
+
+        self.loop_variables.push(name.to_string());
+        let mut control_var = eval;
+        while control_var <= end_value {
+            self.step()?;
+            self.i = body;
+            self.evaluate_block(closer)?;
+
+            if self.options.for_bound == ForBoundMode::Reevaluated {
+                let after_body = self.i;
+                self.i = bound;
+                end_value = self.evaluate_bitwise()?;
+                self.i = after_body;
+            }
+
+            if control_var >= end_value {
+                break;
             }
+
+            control_var += 1;
+            self.write_variable(var, control_var, VariableKind::LoopControl)?;
         }
 
+        self.loop_variables.pop();
+        match shadowed {
+            Some(Some(value)) => { self.variables.insert(name.to_string(), value); },
+            Some(None) => { self.variables.remove(name); },
+            None => ()
+        }
         Ok(0)
     }
 
+    fn evaluate_while(&mut self) -> Result<i64, Error> {
+        let condition = self.i;
+        loop {
+            self.step()?;
+            self.i = condition;
+            let value = self.evaluate_bitwise()?;
+            let closer = match self.block_opener() {
+                Some(closer) => closer,
+                None => return Err(Error::ExpectedStartingBrackets(self.current_token_info()))
+            };
+
+            if value == 0 {
+                self.skip_block()?;
+                return Ok(0);
+            }
+
+            self.evaluate_block(closer)?;
+        }
+    }
+
+    /// Matches `begin` or `{` and returns the token that has to close the block.
+    fn block_opener(&mut self) -> Option<Token> {
+        if self.match_token(Token::Begin) {
+            Some(Token::End)
+        } else if self.match_token(Token::LeftBraces) {
+            Some(Token::RightBraces)
+        } else {
+            None
+        }
+    }
+
+    /// Runs the statements of a block, up to and including `closer`, and gives the value of the
+    /// last one, or 0 for an empty block, so `x := { 1; 2; 3 }` sets x to 3. `self.i` has to be
+    /// on the first token after the opener, which loops rewind to on every iteration.
+    fn evaluate_block(&mut self, closer: Token) -> Result<i64, Error> {
+        let opener = self.i - 1;
+        let other_closer = if closer == Token::End { Token::RightBraces } else { Token::End };
+        self.blocks_entered += 1;
+        self.blocks.push(self.blocks_entered);
+        let value = self.evaluate_statements(opener, closer, other_closer);
+        self.blocks.pop();
+        value
+    }
+
+    fn evaluate_statements(&mut self, opener: usize, closer: Token, other_closer: Token) -> Result<i64, Error> {
+        let mut value = 0;
+        while !self.match_token(closer) {
+            if self.match_token(Token::EOF) {
+                return Err(Error::MissingClosingBrackets(self.current_token_info()));
+            } else if self.match_token(other_closer) {
+                return Err(self.mismatched_block(opener));
+            }
+
+            self.statement()?;
+            value = self.evaluate_statement()?;
+            if self.match_token(closer) {
+                break;
+            } else if self.match_token(other_closer) {
+                return Err(self.mismatched_block(opener));
+            } else {
+                self.end_of_statement()?;
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn mismatched_block(&self, opener: usize) -> Error {
+        Error::MismatchedBlock(self.current_token_info(), Box::new(self.tokens.token_info(opener)))
+    }
+
+    /// Moves past the next top-level statement and its semicolon without running it.
+    fn skip_statement(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.tokens.token(self.i) {
+                Token::LeftParantheses | Token::LeftBraces | Token::Begin => depth += 1,
+                Token::RightParantheses | Token::RightBraces | Token::End => depth = depth.saturating_sub(1),
+                Token::Semicolon if depth == 0 => {
+                    self.i += 1;
+                    return;
+                },
+                Token::EOF => return,
+                _ => {}
+            }
+            self.i += 1;
+        }
+    }
+
+    /// Moves past a block whose opener was just matched without running it.
+    fn skip_block(&mut self) -> Result<(), Error> {
+        let mut depth = 0;
+        loop {
+            match self.tokens.token(self.i) {
+                Token::Begin | Token::LeftBraces => depth += 1,
+                Token::End | Token::RightBraces if depth == 0 => break,
+                Token::End | Token::RightBraces => depth -= 1,
+                Token::EOF => return Err(Error::MissingClosingBrackets(self.tokens.token_info(self.i))),
+                _ => {}
+            }
+            self.i += 1;
+        }
+
+        self.i += 1;
+        Ok(())
+    }
+
+    fn statement_has_effect(&self) -> bool {
+        match self.tokens.token(self.i) {
+            Token::Identifier => self.tokens.token(self.i + 1) == Token::Assignment,
+            Token::Console | Token::Vars | Token::For | Token::While | Token::LeftBraces => true,
+            _ => false
+        }
+    }
+
     fn end_of_statement(&mut self) -> Result<(), Error> {
         if self.match_token(Token::Semicolon) {
             return Ok(());
         }
 
-        Err(Error::MissingSemicolon(self.current_token_info.clone()))
+        Err(Error::MissingSemicolon(self.current_token_info()))
+    }
+}
+
+/// Writes every variable as a `name = value` line, sorted by name so the output is the
+/// same on every run.
+pub fn dump_vars(variables: &HashMap<String, i64>, writer: &mut impl Write) -> std::io::Result<()> {
+    let mut names: Vec<&String> = variables.keys().collect();
+    names.sort();
+    for name in names {
+        writeln!(writer, "{} = {}", name, variables[name])?;
     }
+
+    Ok(())
+}
+
+/// Parses the `Int` or `Hex` literal token at `i` into its value. Leading zeros are accepted
+/// and read as decimal, so `007` is 7.
+fn parse_number<S: Tokens + ?Sized>(tokens: &S, i: usize) -> Result<i64, Error> {
+    if tokens.token(i) != Token::Int && tokens.token(i) != Token::Hex {
+        return Err(Error::InvalidNumber(tokens.token_info(i)));
+    }
+
+    parse_literal(tokens.lexeme(i)).map_err(|error| match error {
+        LiteralError::Overflow => Error::IntegerOverflow(tokens.token_info(i)),
+        _ => Error::InvalidNumber(tokens.token_info(i))
+    })
 }
 
 pub fn parse(tokens: &[TokenInfo], variables: &mut HashMap<String, i64>) -> Result<i64, Error> {
-    let mut parser_info = ParserInfo {
-        tokens,
-        current_token_info: TokenInfo {
-            token: Token::None,
-            lexeme: String::from(""),
-            start_position: Position { row: 1, col: 1 },
-        },
-        i: 0,
-        variables
+    parse_with_options(tokens, variables, Options::default())
+}
+
+pub fn parse_with_options(tokens: &[TokenInfo], variables: &mut HashMap<String, i64>, options: Options) -> Result<i64, Error> {
+    run(tokens, variables, options).map(|output| output.value)
+}
+
+/// Like `parse_with_options`, but also reports how variables were used during the run.
+pub fn run(tokens: &[TokenInfo], variables: &mut HashMap<String, i64>, options: Options) -> Result<RunOutput, Error> {
+    run_tokens(tokens, variables, options)
+}
+
+/// Like `run`, for tokens from `tokenizer::tokenize_compact`.
+pub fn run_compact(tokens: &CompactTokens, variables: &mut HashMap<String, i64>, options: Options) -> Result<RunOutput, Error> {
+    run_tokens(tokens, variables, options)
+}
+
+fn run_tokens<S: Tokens + ?Sized>(tokens: &S, variables: &mut HashMap<String, i64>, options: Options) -> Result<RunOutput, Error> {
+    let mut parser_info = ParserInfo::new(tokens, variables, options);
+
+    let mut result: i64 = 0;
+    while let Some((statement, value)) = parser_info.run_statement()? {
+        result = result.checked_add(value)
+            .ok_or_else(|| Error::ArithmeticOverflow(parser_info.tokens.token_info(statement)))?;
+    }
+
+    let (code_lines, covered_lines) = if parser_info.options.coverage {
+        (parser_info.lines(false), parser_info.lines(true))
+    } else {
+        (BTreeSet::new(), BTreeSet::new())
     };
+    Ok(RunOutput { value: result, accesses: parser_info.accesses, duplicates: parser_info.duplicates, code_lines, covered_lines })
+}
 
-    let mut result = 0;
-    while !parser_info.match_token(Token::EOF) {
-        result += parser_info.evaluate_bitwise()?;
-        if parser_info.match_token(Token::EOF) {
-            break;
-        } else {
-            parser_info.end_of_statement()?;
+/// Keeps variables between runs and can run a program one top-level statement at a time.
+#[derive(Debug, Default, Clone)]
+pub struct Interpreter {
+    pub variables: HashMap<String, i64>,
+    pub options: Options,
+    /// Top-level statements the next `run_iter` skips, for a state saved mid-program.
+    resume_at: usize
+}
+
+/// What running one top-level statement produced. A loop is a single statement, so its
+/// output holds what every iteration printed.
+#[derive(Debug, PartialEq)]
+pub struct StatementOutcome {
+    pub line: u32,
+    pub value: i64,
+    pub console_output: String
+}
+
+pub struct StatementIter<'slice> {
+    parser_info: ParserInfo<'slice>,
+    /// Top-level statements run so far, counting the ones skipped to resume.
+    done: usize
+}
+
+impl Interpreter {
+    pub fn new(options: Options) -> Self {
+        Interpreter { variables: HashMap::new(), options, resume_at: 0 }
+    }
+
+    /// A copy of the current variables and options to run something on without affecting
+    /// this interpreter.
+    pub fn fork(&self) -> Interpreter {
+        self.clone()
+    }
+
+    pub fn eval_parsed(&mut self, program: &ParsedProgram) -> Result<i64, Error> {
+        parse_with_options(program.tokens(), &mut self.variables, self.options)
+    }
+
+    /// Runs `tokens` lazily: every `next()` runs one more statement, with CONSOLE and VARS
+    /// output captured into the outcome instead of printed.
+    /// After `restore_state` of a state saved mid-program, the statements that had already
+    /// run are skipped, so `tokens` has to be the same program.
+    pub fn run_iter<'slice>(&'slice mut self, tokens: &'slice [TokenInfo]) -> StatementIter<'slice> {
+        let done = std::mem::take(&mut self.resume_at);
+        let mut parser_info = ParserInfo::new(tokens, &mut self.variables, self.options);
+        parser_info.captured = Some(Vec::new());
+        for _ in 0..done {
+            parser_info.skip_statement();
         }
+        StatementIter { parser_info, done }
     }
 
-    return Ok(result);
+    /// The variables and options in the `interpreter v1` text format; see `restore_state`.
+    pub fn save_state(&self) -> String {
+        write_state(self.variables.iter(), &self.options, self.resume_at)
+    }
+
+    /// Reads a state written by `save_state` or `StatementIter::save_state`.
+    pub fn restore_state(state: &str) -> Result<Interpreter, StateError> {
+        let mut lines = state.lines();
+        let header = lines.next().unwrap_or_default();
+        if header != format!("interpreter v{}", STATE_VERSION) {
+            return Err(StateError::UnsupportedVersion(header.to_string()));
+        }
+
+        let mut interpreter = Interpreter::default();
+        for (line, number) in lines.zip(2..) {
+            read_state_line(&mut interpreter, line).map_err(|reason| StateError::Malformed(number, reason))?;
+        }
+        Ok(interpreter)
+    }
+}
+
+impl StatementIter<'_> {
+    /// Like `Interpreter::save_state`, but also records how many statements have run, so
+    /// `run_iter` on the restored interpreter carries on with the next one.
+    pub fn save_state(&self) -> String {
+        write_state(self.parser_info.variables.iter(), &self.parser_info.options, self.done)
+    }
+}
+
+pub const STATE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum StateError {
+    UnsupportedVersion(String),
+    Malformed(usize, String)
+}
+
+impl std::error::Error for StateError {}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::UnsupportedVersion(header) =>
+                write!(f, "State error: expected header 'interpreter v{}', found '{}' on line 1", STATE_VERSION, header),
+            StateError::Malformed(line, reason) =>
+                write!(f, "State error: {} on line {}", reason, line)
+        }
+    }
+}
+
+/// A header, then one `name value` line per option, `statement N` and `var "name" value`
+/// for each variable in name order.
+fn write_state<'a>(variables: impl Iterator<Item = (&'a String, &'a i64)>, options: &Options, statement: usize) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| String::from("none"));
+    let mut state = format!("interpreter v{}\n", STATE_VERSION);
+    state += &format!("reject_unused_values {}\n", options.reject_unused_values);
+    state += &format!("group_digits {}\n", options.group_digits);
+    state += &format!("max_steps {}\n", optional(options.max_steps.map(|n| n.to_string())));
+    state += &format!("for_bound {}\n", match options.for_bound {
+        ForBoundMode::Frozen => "frozen",
+        ForBoundMode::Reevaluated => "reevaluated"
+    });
+    state += &format!("max_statements {}\n", optional(options.limits.max_statements.map(|n| n.to_string())));
+    state += &format!("max_expr_depth {}\n", optional(options.limits.max_expr_depth.map(|n| n.to_string())));
+    state += &format!("max_variables {}\n", optional(options.limits.max_variables.map(|n| n.to_string())));
+    state += &format!("permissive_console {}\n", options.permissive_console);
+    state += &format!("strictness {}\n", match options.strictness {
+        Strictness::Legacy => "legacy",
+        Strictness::Strict => "strict"
+    });
+    state += &format!("coverage {}\n", options.coverage);
+    state += &format!("statement {}\n", statement);
+
+    let mut variables: Vec<_> = variables.collect();
+    variables.sort();
+    let mut buffer = Vec::new();
+    for (name, value) in variables {
+        buffer.extend_from_slice(b"var ");
+        write_quoted(&mut buffer, name).expect("writing to a Vec cannot fail");
+        writeln!(buffer, " {}", value).expect("writing to a Vec cannot fail");
+    }
+    state + &String::from_utf8_lossy(&buffer)
+}
+
+fn read_state_line(interpreter: &mut Interpreter, line: &str) -> Result<(), String> {
+    fn value<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+        value.parse().map_err(|_| format!("invalid {} '{}'", name, value))
+    }
+    fn optional<T: std::str::FromStr>(name: &str, text: &str) -> Result<Option<T>, String> {
+        if text == "none" { Ok(None) } else { value(name, text).map(Some) }
+    }
+
+    let (name, rest) = line.split_once(' ').ok_or(format!("expected a value after '{}'", line))?;
+    let options = &mut interpreter.options;
+    match name {
+        "reject_unused_values" => options.reject_unused_values = value(name, rest)?,
+        "group_digits" => options.group_digits = value(name, rest)?,
+        "max_steps" => options.max_steps = optional(name, rest)?,
+        "for_bound" => options.for_bound = match rest {
+            "frozen" => ForBoundMode::Frozen,
+            "reevaluated" => ForBoundMode::Reevaluated,
+            _ => return Err(format!("invalid for_bound '{}'", rest))
+        },
+        "max_statements" => options.limits.max_statements = optional(name, rest)?,
+        "max_expr_depth" => options.limits.max_expr_depth = optional(name, rest)?,
+        "max_variables" => options.limits.max_variables = optional(name, rest)?,
+        "permissive_console" => options.permissive_console = value(name, rest)?,
+        "strictness" => options.strictness = match rest {
+            "legacy" => Strictness::Legacy,
+            "strict" => Strictness::Strict,
+            _ => return Err(format!("invalid strictness '{}'", rest))
+        },
+        "coverage" => options.coverage = value(name, rest)?,
+        "statement" => interpreter.resume_at = value(name, rest)?,
+        "var" => {
+            // the quoted name may contain spaces, the value never does
+            let (quoted, number) = rest.rsplit_once(' ').ok_or(String::from("missing variable value"))?;
+            interpreter.variables.insert(read_quoted(quoted, "variable name")?, value("variable value", number)?);
+        },
+        _ => return Err(format!("unknown entry '{}'", name))
+    }
+    Ok(())
+}
+
+impl Iterator for StatementIter<'_> {
+    type Item = Result<StatementOutcome, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parser_info = &mut self.parser_info;
+        let outcome = parser_info.run_statement().map(|statement| statement.map(|(statement, value)| {
+            let captured = parser_info.captured.as_mut().map(std::mem::take).unwrap_or_default();
+            StatementOutcome {
+                line: parser_info.tokens.start_position(statement).row,
+                value,
+                console_output: String::from_utf8_lossy(&captured).into_owned()
+            }
+        }));
+
+        match outcome {
+            Ok(outcome) => {
+                self.done += outcome.is_some() as usize;
+                outcome.map(Ok)
+            },
+            Err(error) => {
+                parser_info.finished = true;
+                Some(Err(error))
+            }
+        }
+    }
 }
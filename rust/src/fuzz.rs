@@ -0,0 +1,33 @@
+use crate::{tokenizer, parser, eval};
+use std::collections::HashMap;
+
+/// How far a fuzz input got through the pipeline.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    TokenizerError,
+    SyntaxError,
+    EvaluationError,
+    Evaluated(i64)
+}
+
+/// Statements and loop iterations a single input may run before it is stopped.
+pub const FUEL: u64 = 10_000;
+
+/// Runs `input` through tokenize, parse and eval the same way main does, with a step limit so
+/// looping programs terminate. Any panic in here is a bug.
+pub fn fuzz_one(input: &[u8]) -> Outcome {
+    let tokens = match tokenizer::tokenize(input) {
+        Ok(tokens) => tokens,
+        Err(_) => return Outcome::TokenizerError
+    };
+
+    if parser::parse(&tokens, parser::DEFAULT_MAX_DEPTH).is_err() {
+        return Outcome::SyntaxError;
+    }
+
+    let options = eval::Options { max_steps: Some(FUEL), ..Default::default() };
+    match eval::parse_with_options(&tokens, &mut HashMap::new(), options) {
+        Ok(value) => Outcome::Evaluated(value),
+        Err(_) => Outcome::EvaluationError
+    }
+}
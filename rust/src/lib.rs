@@ -1,3 +1,73 @@
 pub mod tokenizer;
 pub mod parser;
 pub mod eval;
+pub mod stats;
+pub mod literal;
+pub mod diagnostics;
+pub mod analysis;
+mod context;
+#[doc(hidden)]
+pub mod fuzz;
+
+use diagnostics::Diagnostics;
+use std::collections::HashMap;
+
+/// Options for every phase, e.g. as the command line sets them.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RunOptions {
+    pub tokenizer: tokenizer::Options,
+    pub parser: parser::Options,
+    pub eval: eval::Options
+}
+
+/// Tokenizes, checks and evaluates `source`, stopping at the first error.
+pub fn run_str(source: &str, variables: &mut HashMap<String, i64>) -> Result<i64, Box<dyn std::error::Error>> {
+    let tokens = tokenizer::tokenize(source.as_bytes())?;
+    parser::parse(&tokens, parser::DEFAULT_MAX_DEPTH)?;
+    Ok(eval::parse(&tokens, variables)?)
+}
+
+/// Like `run_str`, but keeps going past bad characters and collects every problem found
+/// instead of returning the first. The program is only evaluated when no errors were found.
+/// Bad characters are recovered from unless `options.tokenizer` is strict.
+pub fn run_str_collect(source: &str, variables: &mut HashMap<String, i64>, options: RunOptions) -> (Option<eval::RunOutput>, Diagnostics) {
+    let mut diagnostics = Diagnostics::default();
+    let tokenizer_options = tokenizer::Options { recover_invalid_characters: true, ..options.tokenizer };
+    let tokens = match tokenizer::tokenize_with_options(source.as_bytes(), tokenizer_options) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            diagnostics.error(tokenizer::Position { row: 1, col: 1 }, error.to_string());
+            return (None, diagnostics);
+        }
+    };
+
+    for token_info in tokens.iter().filter(|token_info| token_info.token == tokenizer::Token::Error) {
+        let error = tokenizer::Error::InvalidPattern(token_info.lexeme.clone(), token_info.start_position);
+        diagnostics.error(token_info.start_position, error.to_string());
+    }
+
+    match parser::parse_with_options(&tokens, options.parser) {
+        Err(error) => diagnostics.error(error.token_info().start_position, error.to_string()),
+        Ok(()) => {
+            for condition in analysis::constant_while_conditions(&tokens) {
+                diagnostics.warning(condition.position, condition.to_string());
+            }
+        }
+    }
+
+    let mut result = None;
+    if !diagnostics.has_errors() {
+        match eval::run(&tokens, variables, options.eval) {
+            Ok(output) => {
+                for duplicate in output.duplicate_assignments() {
+                    diagnostics.warning(duplicate.second, duplicate.to_string());
+                }
+                result = Some(output);
+            },
+            Err(error) => diagnostics.error(error.token_info().start_position, error.to_string())
+        }
+    }
+
+    diagnostics.sort_by_position();
+    (result, diagnostics)
+}
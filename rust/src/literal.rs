@@ -0,0 +1,76 @@
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LiteralRadix {
+    Decimal,
+    /// `#`-prefixed, with uppercase digits.
+    Hex
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LiteralError {
+    Empty,
+    InvalidDigit,
+    Overflow
+}
+
+impl std::error::Error for LiteralError {}
+
+impl std::fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LiteralError::Empty => write!(f, "Literal error: empty literal"),
+            LiteralError::InvalidDigit => write!(f, "Literal error: invalid digit in literal"),
+            LiteralError::Overflow => write!(f, "Literal error: literal does not fit into a 64-bit integer")
+        }
+    }
+}
+
+/// Writes `value` the way it would be written in a program. Negative values get a leading
+/// `-` in front of the `#`, so -255 in hex is `-#FF`.
+pub fn format_literal(value: i64, radix: LiteralRadix) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    match radix {
+        LiteralRadix::Decimal => value.to_string(),
+        LiteralRadix::Hex => format!("{}#{:X}", sign, value.unsigned_abs())
+    }
+}
+
+/// Writes `value` in decimal with `_` between groups of three digits, e.g. `-1_000_000`.
+pub fn group_digits(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push('_');
+        }
+        grouped.push(digit);
+    }
+
+    if value < 0 {
+        format!("-{}", grouped)
+    } else {
+        grouped
+    }
+}
+
+/// Inverse of `format_literal`. Hex digits may be in either case and leading zeros are
+/// accepted in both radixes.
+pub fn parse_literal(literal: &str) -> Result<i64, LiteralError> {
+    let (sign, unsigned) = match literal.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", literal)
+    };
+    let (radix, digits) = match unsigned.strip_prefix('#') {
+        Some(rest) => (16, rest),
+        None => (10, unsigned)
+    };
+
+    if digits.is_empty() {
+        return Err(LiteralError::Empty);
+    }
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(LiteralError::InvalidDigit);
+    }
+
+    // parsing with the sign attached is what lets i64::MIN through
+    i64::from_str_radix(&format!("{}{}", sign, digits), radix).map_err(|_| LiteralError::Overflow)
+}
@@ -1,27 +1,281 @@
 use rust::tokenizer;
 use rust::parser;
 use rust::eval;
+use rust::stats;
+use rust::literal;
 use std::fs::File;
 use std::io::BufReader;
 use std::collections::HashMap;
 
+const USAGE: &str = "\
+Usage: rust [OPTIONS] FILE...
+
+Tokenizes, checks and evaluates each FILE in order, sharing variables between them.
+x and y start out as 1 and 3.
+
+Options:
+  --stats                 print token statistics instead of running
+  --check                 only check the syntax, without running
+  --collect               report every tokenizer, parser and evaluation problem found
+  --warn-unused           warn about variables that are assigned but never read
+  --coverage              print each file with a gutter marking lines that ran (✓) or not (·)
+  --scenarios PATH        run each FILE once per line of PATH, each time with its own variables;
+                          a line is a comma-separated list of name=value pairs, e.g. x=5, n=#A
+  --permissive-console    allow CONSOLE inside expressions and assignments in its argument
+  --reject-unused-values  reject top-level statements whose value is neither assigned nor printed
+  --strict                turn off every lenient legacy behaviour, including --permissive-console,
+                          reject assignments inside expressions and unused values like
+                          --reject-unused-values, and keep for loop variables local to their loop
+  --max-steps N           stop after N statements and loop iterations
+  --max-statements N      stop after N statements
+  --max-depth N           reject expressions nested deeper than N, up to 256, when parsing,
+                          and stop at that depth while evaluating unless --max-expr-depth is given
+  --max-expr-depth N      stop when expressions nest deeper than N, up to 256, while evaluating
+  --max-variables N       stop when more than N variables would exist
+  --help                  print this help
+  --version               print the version
+";
+
+/// One line of a `--scenarios` file.
+struct Scenario {
+    line: usize,
+    variables: Vec<(String, i64)>
+}
+
 fn main() {
-    let mut variables = HashMap::new();
-    variables.insert(String::from("x"), 1);
-    variables.insert(String::from("y"), 3);
+    let mut variables = default_variables();
+
+    let mut args = std::env::args().skip(1).peekable();
+    let mut show_stats = false;
+    let mut check = false;
+    let mut collect = false;
+    let mut warn_unused = false;
+    let mut scenarios = None;
+    let mut tokenizer_options = tokenizer::Options::default();
+    let mut parser_options = parser::Options::default();
+    let mut options = eval::Options::default();
+    while let Some(flag) = args.next_if(|arg| arg.starts_with("--")) {
+        match flag.as_str() {
+            "--help" => {
+                print!("{}", USAGE);
+                return;
+            },
+            "--version" => {
+                println!("rust {}", env!("CARGO_PKG_VERSION"));
+                return;
+            },
+            "--stats" => show_stats = true,
+            "--check" => check = true,
+            "--collect" => collect = true,
+            "--warn-unused" => warn_unused = true,
+            "--coverage" => options.coverage = true,
+            "--scenarios" => {
+                let path = args.next().unwrap_or_else(|| {
+                    eprintln!("Usage: --scenarios expects a file");
+                    std::process::exit(2);
+                });
+                let text = std::fs::read_to_string(&path).expect("Error opening file.");
+                match parse_scenarios(&text) {
+                    Ok(parsed) => scenarios = Some(parsed),
+                    Err(error) => {
+                        println!("\n{} in file {}", error, path);
+                        std::process::exit(1);
+                    }
+                }
+            },
+            "--max-steps" => options.max_steps = Some(flag_value(&flag, args.next())),
+            "--max-depth" => {
+                let max_depth = depth_value(&flag, args.next());
+                parser_options.max_depth = max_depth as usize;
+                options.limits.max_expr_depth.get_or_insert(max_depth);
+            },
+            "--permissive-console" => {
+                parser_options.permissive_console = true;
+                options.permissive_console = true;
+            },
+            "--reject-unused-values" => options.reject_unused_values = true,
+            "--strict" => {
+                tokenizer_options.strictness = tokenizer::Strictness::Strict;
+                parser_options.strictness = tokenizer::Strictness::Strict;
+                options.strictness = tokenizer::Strictness::Strict;
+            },
+            "--max-statements" => options.limits.max_statements = Some(flag_value(&flag, args.next())),
+            "--max-expr-depth" => options.limits.max_expr_depth = Some(depth_value(&flag, args.next())),
+            "--max-variables" => options.limits.max_variables = Some(flag_value(&flag, args.next())),
+            _ => {
+                eprintln!("Unknown option {}\n\n{}", flag, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let run_options = rust::RunOptions { tokenizer: tokenizer_options, parser: parser_options, eval: options };
+    let mut failed = false;
+    for arg in args {
+        if check {
+            let source = std::fs::read(&arg).expect("Error opening file.");
+            if let Err(error) = check_syntax(&source, tokenizer_options, parser_options) {
+                println!("\n{} in file {}", error, arg);
+                failed = true;
+            }
+            continue;
+        }
+
+        if collect {
+            let source = std::fs::read_to_string(&arg).expect("Error opening file.");
+            let (_, diagnostics) = rust::run_str_collect(&source, &mut variables, run_options);
+            if !diagnostics.is_empty() {
+                print!("\n{} in file {}:\n{}", diagnostics.len(), arg, diagnostics.render(&source));
+            }
+            failed |= diagnostics.has_errors();
+            continue;
+        }
 
-    for arg in std::env::args().into_iter().skip(1) {
         let mut reader = BufReader::new(File::open(&arg).expect("Error opening file."));
 
-        match tokenizer::tokenize(&mut reader) {
-            Err(error) => println!("\n{} in file {}", error, arg),
-            Ok(tokens) => match parser::parse(&tokens) {
-                Err(error) => println!("\n{} in file {}", error, arg),
-                _ => match eval::parse(&tokens, &mut variables) {
-                    Err(error) => println!("\n{} in file {}", error, arg),
-                    Ok(_) => ()
+        let error: Option<Box<dyn std::error::Error>> = match tokenizer::tokenize_with_options(&mut reader, tokenizer_options) {
+            Err(error) => Some(error.into()),
+            Ok(tokens) if show_stats => {
+                print!("{}", stats::stats(&tokens));
+                None
+            },
+            Ok(tokens) => match parser::parse_with_options(&tokens, parser_options) {
+                Err(error) => Some(error.into()),
+                _ => match &scenarios {
+                    Some(scenarios) => {
+                        failed |= !run_scenarios(&arg, &tokens, scenarios, options);
+                        None
+                    },
+                    None => match eval::run(&tokens, &mut variables, options) {
+                        Err(error) => Some(error.into()),
+                        Ok(output) => {
+                            if warn_unused {
+                                for (name, position) in output.unused_variables() {
+                                    println!("Warning: variable '{}' assigned on line {} is never read in file {}", name, position.row, arg);
+                                }
+                            }
+                            if options.coverage {
+                                print_coverage(&arg, &output);
+                            }
+                            None
+                        }
+                    }
                 }
             }
         };
+
+        if let Some(error) = error {
+            println!("\n{} in file {}", error, arg);
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// The variables every program starts out with.
+fn default_variables() -> HashMap<String, i64> {
+    HashMap::from([(String::from("x"), 1), (String::from("y"), 3)])
+}
+
+/// Reads one scenario per non-blank line, numbering them by line.
+fn parse_scenarios(text: &str) -> Result<Vec<Scenario>, String> {
+    let mut scenarios = Vec::new();
+    for (line, content) in (1..).zip(text.lines()) {
+        if content.trim().is_empty() {
+            continue;
+        }
+
+        let mut variables = Vec::new();
+        for pair in content.split(',') {
+            let (name, value) = pair.split_once('=')
+                .ok_or(format!("Scenario error: expected name=value, found '{}' on line {}", pair.trim(), line))?;
+            let (name, value) = (name.trim(), value.trim());
+            let is_identifier = matches!(tokenizer::tokenize(name.as_bytes()).as_deref(),
+                Ok([token_info, _]) if token_info.token == tokenizer::Token::Identifier && token_info.lexeme == name);
+            if !is_identifier {
+                return Err(format!("Scenario error: '{}' is not a variable name on line {}", name, line));
+            }
+            let value = literal::parse_literal(value)
+                .map_err(|error| format!("Scenario error: bad value for '{}' on line {} ({})", name, line, error))?;
+            variables.push((name.to_string(), value));
+        }
+        scenarios.push(Scenario { line, variables });
+    }
+
+    Ok(scenarios)
+}
+
+/// Evaluates the already parsed `tokens` once per scenario, each time starting from the
+/// default variables with the scenario's on top. Returns whether every scenario ran cleanly.
+fn run_scenarios(path: &str, tokens: &[tokenizer::TokenInfo], scenarios: &[Scenario], options: eval::Options) -> bool {
+    let mut succeeded = true;
+    for scenario in scenarios {
+        let seeds: Vec<String> = scenario.variables.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        println!("Scenario {} ({}):", scenario.line, seeds.join(", "));
+
+        let mut variables = default_variables();
+        variables.extend(scenario.variables.iter().cloned());
+        if let Err(error) = eval::run(tokens, &mut variables, options) {
+            println!("\n{} in file {}", error, path);
+            succeeded = false;
+        }
+    }
+
+    succeeded
+}
+
+/// Checks with slim tokens, which is faster, and only tokenizes again in full to describe
+/// an error.
+fn check_syntax(source: &[u8], tokenizer_options: tokenizer::Options, parser_options: parser::Options) -> Result<(), Box<dyn std::error::Error>> {
+    let valid = tokenizer::tokenize_slim(source, tokenizer_options)
+        .is_ok_and(|tokens| parser::parse_slim(&tokens, parser_options).is_ok());
+    if !valid {
+        let tokens = tokenizer::tokenize_with_options(source, tokenizer_options)?;
+        parser::parse_with_options(&tokens, parser_options)?;
+    }
+    Ok(())
+}
+
+fn print_coverage(path: &str, output: &eval::RunOutput) {
+    let source = std::fs::read_to_string(path).expect("Error opening file.");
+    let covered = output.covered_lines();
+    let uncovered = output.uncovered_lines();
+    println!("Coverage of {}: {}/{} lines", path, covered.len(), covered.len() + uncovered.len());
+    for (row, line) in (1..).zip(source.lines()) {
+        let gutter = if covered.contains(&row) {
+            '✓'
+        } else if uncovered.contains(&row) {
+            '·'
+        } else {
+            ' '
+        };
+        println!("{} {}", gutter, line);
+    }
+}
+
+/// The deepest nesting `--max-depth` and `--max-expr-depth` accept. Nested for loops recurse
+/// the most per level, and a debug build runs out of its 8 MiB main thread stack on them
+/// somewhere between 512 and 600 levels.
+const MAX_DEPTH_LIMIT: u32 = 256;
+
+fn depth_value(flag: &str, value: Option<String>) -> u32 {
+    let depth = flag_value(flag, value);
+    if depth > MAX_DEPTH_LIMIT {
+        eprintln!("Usage: {} expects a number no greater than {}", flag, MAX_DEPTH_LIMIT);
+        std::process::exit(2);
+    }
+    depth
+}
+
+fn flag_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(value)) => value,
+        _ => {
+            eprintln!("Usage: {} expects a non-negative number", flag);
+            std::process::exit(2);
+        }
     }
 }
@@ -1,4 +1,5 @@
-use crate::tokenizer::{TokenInfo, Token, Position};
+use crate::tokenizer::{TokenInfo, Token, SlimToken, CompactTokens, Tokens, Strictness, RESERVED_WORDS};
+use crate::context::{error_context, doubled_operator};
 
 #[derive(Debug)]
 pub enum Error {
@@ -9,7 +10,15 @@ pub enum Error {
     MissingClosingParantheses(TokenInfo),
     ExpectedStartingBrackets(TokenInfo),
     ExpectedStartingParantheses(TokenInfo),
-    MissingSemicolon(TokenInfo)
+    MissingSemicolon(TokenInfo),
+    NestingTooDeep(TokenInfo),
+    ReservedWord(TokenInfo),
+    /// The closer that was found, and the opener of the block it doesn't match.
+    MismatchedBlock(TokenInfo, Box<TokenInfo>),
+    /// Two binary operators in a row: the first and the one after it.
+    DoubledOperator(TokenInfo, Box<TokenInfo>),
+    /// A statement starting with an operator that can't be a sign.
+    ExpressionExpected(TokenInfo)
 }
 
 impl std::error::Error for Error {}
@@ -18,11 +27,11 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Generic(token_info, string) =>
-                write!(f, "Syntax error: unexpected token '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token.to_string(), string, token_info.start_position.row),
+                write!(f, "Syntax error: unexpected token '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token, string, token_info.start_position.row),
             Error::InvalidFor(token_info) =>
-                write!(f, "Syntax error: invalid for loop structure, unexpected token '{}' of type {} on line {}", token_info.lexeme, token_info.token.to_string(), token_info.start_position.row),
+                write!(f, "Syntax error: invalid for loop structure, unexpected token '{}' of type {} on line {}", token_info.lexeme, token_info.token, token_info.start_position.row),
             Error::InvalidAssignment(token_info, string) =>
-                write!(f, "Syntax error: invalid assignment; found '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token.to_string(), string, token_info.start_position.row),
+                write!(f, "Syntax error: invalid assignment; found '{}' of type {} after {} on line {}", token_info.lexeme, token_info.token, string, token_info.start_position.row),
             Error::MissingClosingBrackets(token_info) =>
                 write!(f, "Syntax error: missing closing brackets on line {}", token_info.start_position.row),
             Error::MissingClosingParantheses(token_info) =>
@@ -32,22 +41,93 @@ impl std::fmt::Display for Error {
             Error::ExpectedStartingParantheses(token_info) =>
                 write!(f, "Syntax error: expected (, found '{}' on line {}", token_info.lexeme, token_info.start_position.row),
             Error::MissingSemicolon(token_info) =>
-                write!(f, "Syntax error: missing semicolon ';' on line {}", token_info.start_position.row)
+                write!(f, "Syntax error: missing semicolon ';' on line {}", token_info.start_position.row),
+            Error::NestingTooDeep(token_info) =>
+                write!(f, "Syntax error: expression nested too deeply at '{}' on line {}", token_info.lexeme, token_info.start_position.row),
+            Error::MismatchedBlock(token_info, opener) =>
+                write!(f, "Syntax error: found '{}' on line {} but block was opened with '{}' on line {}", token_info.lexeme, token_info.start_position.row, opener.lexeme, opener.start_position.row),
+            Error::ExpressionExpected(token_info) =>
+                write!(f, "Syntax error: expected a value or '(' at the start of a statement, found '{}' on line {}", token_info.lexeme, token_info.start_position.row),
+            Error::DoubledOperator(token_info, second) =>
+                write!(f, "Syntax error: operator '{}' on line {} is followed by another operator '{}' on line {}", token_info.lexeme, token_info.start_position.row, second.lexeme, second.start_position.row),
+            Error::ReservedWord(token_info) =>
+                write!(f, "Syntax error: '{}' is a reserved word and cannot be used as a variable name on line {}; reserved words are {}", token_info.lexeme, token_info.start_position.row, RESERVED_WORDS.map(|(word, _)| word).join(", "))
+        }
+    }
+}
+
+/// Deep enough for any real program, and shallow enough that parsing and evaluating it fits
+/// in the 2 MiB stack of a spawned thread, e.g. a test, in a debug build.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
 
+impl Error {
+    pub fn token_info(&self) -> &TokenInfo {
+        match self {
+            Error::Generic(token_info, _) | Error::InvalidAssignment(token_info, _) => token_info,
+            Error::InvalidFor(token_info) | Error::MissingClosingBrackets(token_info) |
+            Error::MissingClosingParantheses(token_info) | Error::ExpectedStartingBrackets(token_info) |
+            Error::ExpectedStartingParantheses(token_info) | Error::MissingSemicolon(token_info) |
+            Error::NestingTooDeep(token_info) | Error::ReservedWord(token_info) |
+            Error::MismatchedBlock(token_info, _) | Error::DoubledOperator(token_info, _) |
+            Error::ExpressionExpected(token_info) => token_info
         }
     }
 }
 
-struct ParserInfo<'slice> {
-    tokens:  &'slice [TokenInfo],
-    current_token_info: TokenInfo,
-    i: usize
+/// Result of checking a possibly unfinished piece of input, e.g. a REPL line.
+#[derive(Debug)]
+pub enum ParseOutcome {
+    Complete,
+    /// The input ran out while a construct was still open, so more input may complete it.
+    Incomplete,
+    Error(Error)
 }
 
-impl ParserInfo<'_> {
+#[derive(Debug, Copy, Clone)]
+pub struct Options {
+    pub max_depth: usize,
+    /// Accept CONSOLE anywhere an expression may appear and assignments inside its argument,
+    /// as older course material does, instead of only as a statement printing a pure expression.
+    pub permissive_console: bool,
+    /// `Strict` ignores `permissive_console` and rejects assignments inside expressions; see
+    /// `tokenizer::Strictness`.
+    pub strictness: Strictness
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { max_depth: DEFAULT_MAX_DEPTH, permissive_console: false, strictness: Strictness::Legacy }
+    }
+}
+
+impl Options {
+    fn effective(mut self) -> Self {
+        if self.strictness == Strictness::Strict {
+            self.permissive_console = false;
+        }
+        self
+    }
+}
+
+struct ParserInfo<'slice, S: ?Sized> {
+    tokens: &'slice S,
+    current: usize,
+    i: usize,
+    depth: usize,
+    options: Options,
+    in_console: bool,
+    /// First token of the innermost statement, whose own assignment Strict allows.
+    statement_start: usize
+}
+
+impl<S: Tokens + ?Sized> ParserInfo<'_, S> {
+    fn current_token_info(&self) -> TokenInfo {
+        self.tokens.token_info(self.current)
+    }
+
     fn match_token(&mut self, expected_token: Token) -> bool {
-        self.current_token_info = self.tokens[self.i].clone();
-        if self.tokens[self.i].token == expected_token {
+        self.current = self.i;
+        if self.tokens.token(self.i) == expected_token {
             self.i += 1;
             return true;
         }
@@ -55,45 +135,44 @@ impl ParserInfo<'_> {
         false
     }
 
-    /*fn statement(&mut self, expected_tokens: &[Token]) -> Result<(), Error> {
-        for &expected_token in expected_tokens {
-            if !self.match_token(expected_token) {
-                return Err(Error::Generic(self.current_token_info.clone(), self.last_n_token_lexemes(3)));
-            }
-        }
+    fn error_context(&self) -> String {
+        error_context(self.tokens, self.i)
+    }
+}
 
-        return Ok(());
-    }*/
+pub fn parse(tokens: &[TokenInfo], max_depth: usize) -> Result<(), Error> {
+    parse_with_options(tokens, Options { max_depth, ..Default::default() })
+}
 
-    fn last_n_token_lexemes(&self, n: u32) -> String {
-        let mut counter = 1;
-        let mut string: String = String::from("");
-        while n > 0 {
-            string = format!("{} {}", &string, self.tokens[self.i - counter].lexeme);
-            counter += 1;
+pub fn parse_with_options(tokens: &[TokenInfo], options: Options) -> Result<(), Error> {
+    parse_tokens(tokens, options)
+}
 
-            if self.i - counter == 0 {
-                break;
-            }
-        }
+/// Accepts and rejects exactly what `parse_with_options` does, for tokens from
+/// `tokenizer::tokenize_slim`. The lexemes in the error are empty, so tokenize and parse the
+/// program again the usual way to report it.
+pub fn parse_slim(tokens: &[SlimToken], options: Options) -> Result<(), Error> {
+    parse_tokens(tokens, options)
+}
 
-        string.chars().rev().collect::<String>()
-    }
+/// Like `parse_with_options`, for tokens from `tokenizer::tokenize_compact`.
+pub fn parse_compact(tokens: &CompactTokens, options: Options) -> Result<(), Error> {
+    parse_tokens(tokens, options)
 }
 
-pub fn parse(tokens: &[TokenInfo]) -> Result<(), Error> {
+fn parse_tokens<S: Tokens + ?Sized>(tokens: &S, options: Options) -> Result<(), Error> {
     let mut parser_info = ParserInfo {
         tokens,
-        current_token_info: TokenInfo {
-            token: Token::None,
-            lexeme: String::from(""),
-            start_position: Position { row: 1, col: 1 }
-        },
-        i: 0
+        current: 0,
+        i: 0,
+        depth: 0,
+        options: options.effective(),
+        in_console: false,
+        statement_start: 0
     };
 
     while !parser_info.match_token(Token::EOF) {
-        bitwise(&mut parser_info)?;
+        statement(&mut parser_info)?;
         if parser_info.match_token(Token::EOF) {
             break;
         } else {
@@ -104,16 +183,82 @@ pub fn parse(tokens: &[TokenInfo]) -> Result<(), Error> {
     Ok(())
 }
 
-fn bitwise(parser_info: &mut ParserInfo) -> Result<(), Error> {
+/// Tokens that passed `parse`, so they can be evaluated any number of times without being
+/// checked again.
+#[derive(Debug, Clone)]
+pub struct ParsedProgram {
+    tokens: Vec<TokenInfo>
+}
+
+impl ParsedProgram {
+    pub fn tokens(&self) -> &[TokenInfo] {
+        &self.tokens
+    }
+}
+
+pub fn parse_program(tokens: Vec<TokenInfo>, options: &Options) -> Result<ParsedProgram, Error> {
+    parse_with_options(&tokens, *options)?;
+    Ok(ParsedProgram { tokens })
+}
+
+/// Distinguishes input that merely stops early (the parser failed on the closing `EOF`
+/// token, as in `x := (1 +`) from input that is wrong no matter what follows.
+pub fn classify(tokens: &[TokenInfo]) -> ParseOutcome {
+    match parse(tokens, DEFAULT_MAX_DEPTH) {
+        Ok(()) => ParseOutcome::Complete,
+        Err(error) if error.token_info().token == Token::EOF => ParseOutcome::Incomplete,
+        Err(error) => ParseOutcome::Error(error)
+    }
+}
+
+fn statement<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
+    let first = parser_info.tokens.token(parser_info.i);
+    if first.is_binary_operator() && !matches!(first, Token::Addition | Token::Subtraction) {
+        return Err(Error::ExpressionExpected(parser_info.tokens.token_info(parser_info.i)));
+    }
+
+    parser_info.statement_start = parser_info.i;
+    if !parser_info.options.permissive_console && parser_info.match_token(Token::Console) {
+        return console_argument(parser_info);
+    }
+
+    bitwise(parser_info)
+}
+
+fn console_argument<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
+    // for, while, CONSOLE and VARS are expressions themselves; the other reserved words
+    // can only be meant as a variable here
+    let argument = parser_info.tokens.token(parser_info.i);
+    if argument == Token::Assignment {
+        // CONSOLE := 5
+        return Err(Error::ReservedWord(parser_info.current_token_info()));
+    } else if matches!(argument, Token::In | Token::Begin | Token::End | Token::To) {
+        return Err(Error::ReservedWord(parser_info.tokens.token_info(parser_info.i)));
+    }
+
+    let in_console = std::mem::replace(&mut parser_info.in_console, !parser_info.options.permissive_console);
+    bitwise(parser_info)?;
+    parser_info.in_console = in_console;
+
+    Ok(())
+}
+
+fn bitwise<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
+    if parser_info.depth >= parser_info.options.max_depth {
+        return Err(Error::NestingTooDeep(parser_info.tokens.token_info(parser_info.i)));
+    }
+
+    parser_info.depth += 1;
     addition(parser_info)?;
     while parser_info.match_token(Token::BWAnd) || parser_info.match_token(Token::BWOr) {
         addition(parser_info)?;
     }
+    parser_info.depth -= 1;
 
     Ok(())
 }
 
-fn addition(parser_info: &mut ParserInfo) -> Result<(), Error> {
+fn addition<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
     multiplication(parser_info)?;
     while parser_info.match_token(Token::Addition) || parser_info.match_token(Token::Subtraction) {
         multiplication(parser_info)?;
@@ -122,7 +267,7 @@ fn addition(parser_info: &mut ParserInfo) -> Result<(), Error> {
     Ok(())
 }
 
-fn multiplication(parser_info: &mut ParserInfo) -> Result<(), Error> {
+fn multiplication<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
     comparison_operators(parser_info)?;
     while parser_info.match_token(Token::Multiplication) || parser_info.match_token(Token::Division) {
         comparison_operators(parser_info)?;
@@ -131,7 +276,7 @@ fn multiplication(parser_info: &mut ParserInfo) -> Result<(), Error> {
     Ok(())
 }
 
-fn comparison_operators(parser_info: &mut ParserInfo) -> Result<(), Error> {
+fn comparison_operators<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
     unary(parser_info)?;
     while parser_info.match_token(Token::GreaterThan) || parser_info.match_token(Token::LowerThan) || parser_info.match_token(Token::Comparison) {
         unary(parser_info)?;
@@ -140,43 +285,68 @@ fn comparison_operators(parser_info: &mut ParserInfo) -> Result<(), Error> {
     Ok(())
 }
 
-fn assignment(parser_info: &mut ParserInfo) -> Result<(), Error> {
+fn assignment<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
     if parser_info.match_token(Token::Identifier) && parser_info.match_token(Token::Assignment) {
         return bitwise(parser_info);
     }
 
-    Err(Error::InvalidAssignment(parser_info.current_token_info.clone(), parser_info.last_n_token_lexemes(3)))
+    Err(Error::InvalidAssignment(parser_info.current_token_info(), parser_info.error_context()))
 }
 
-fn end_of_statement(parser_info: &mut ParserInfo) -> Result<(), Error> {
+fn end_of_statement<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
     if parser_info.match_token(Token::Semicolon) {
         return Ok(());
     }
 
-    Err(Error::MissingSemicolon(parser_info.current_token_info.clone()))
+    Err(Error::MissingSemicolon(parser_info.current_token_info()))
 }
 
-fn unary(parser_info: &mut ParserInfo) -> Result<(), Error> {
-    if parser_info.match_token(Token::Addition) || parser_info.match_token(Token::Subtraction) {
-        primary(parser_info)
-    } else {
-        primary(parser_info)
+fn unary<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
+    if doubled_operator(parser_info.tokens, parser_info.i) {
+        let first = parser_info.tokens.token_info(parser_info.i - 1);
+        return Err(Error::DoubledOperator(first, Box::new(parser_info.tokens.token_info(parser_info.i))));
+    }
+
+    if !parser_info.match_token(Token::Addition) {
+        parser_info.match_token(Token::Subtraction);
     }
+
+    primary(parser_info)
 }
 
-fn primary(parser_info: &mut ParserInfo) -> Result<(), Error> {
+fn primary<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Result<(), Error> {
+    if parser_info.tokens.token(parser_info.i).is_reserved_word() && parser_info.tokens.token(parser_info.i + 1) == Token::Assignment {
+        return Err(Error::ReservedWord(parser_info.tokens.token_info(parser_info.i)));
+    }
+
     if parser_info.match_token(Token::Int) || parser_info.match_token(Token::Hex) {
         Ok(())
     } else if parser_info.match_token(Token::Identifier) {
+        // the right-hand side is a full expression, so a := b := 5 nests to the right
         if parser_info.match_token(Token::Assignment) {
+            let nested = parser_info.current - 1 != parser_info.statement_start;
+            if parser_info.in_console || (nested && parser_info.options.strictness == Strictness::Strict) {
+                return Err(Error::InvalidAssignment(parser_info.current_token_info(), parser_info.error_context()));
+            }
+
             bitwise(parser_info)
+        } else if parser_info.match_token(Token::LeftParantheses) {
+            bitwise(parser_info)?;
+            while parser_info.match_token(Token::Comma) {
+                bitwise(parser_info)?;
+            }
+            if !parser_info.match_token(Token::RightParantheses) {
+                return Err(Error::MissingClosingParantheses(parser_info.current_token_info()));
+            }
+
+            Ok(())
         } else {
             Ok(())
         }
     } else if parser_info.match_token(Token::LeftParantheses) {
         bitwise(parser_info)?;
         if !parser_info.match_token(Token::RightParantheses) {
-            return Err(Error::MissingClosingParantheses(parser_info.current_token_info.clone()));
+            return Err(Error::MissingClosingParantheses(parser_info.current_token_info()));
         }
 
         Ok(())
@@ -184,58 +354,75 @@ fn primary(parser_info: &mut ParserInfo) -> Result<(), Error> {
         if parser_info.match_token(Token::LeftParantheses) {
             assignment(parser_info)?;
             if !parser_info.match_token(Token::To) {
-                return Err(Error::InvalidFor(parser_info.current_token_info.clone()));
+                return Err(Error::InvalidFor(parser_info.current_token_info()));
             }
 
             bitwise(parser_info)?;
 
             if !parser_info.match_token(Token::RightParantheses) {
-                return Err(Error::MissingClosingParantheses(parser_info.current_token_info.clone()));
+                return Err(Error::MissingClosingParantheses(parser_info.current_token_info()));
             }
 
-            if !parser_info.match_token(Token::Begin) {
-                return Err(Error::MissingClosingParantheses(parser_info.current_token_info.clone()));
+            match block_opener(parser_info) {
+                Some(closer) => block(parser_info, closer),
+                None => Err(Error::InvalidFor(parser_info.current_token_info()))
             }
-
-            while !parser_info.match_token(Token::End) {
-                bitwise(parser_info)?;
-
-                if parser_info.match_token(Token::End) {
-                    break;
-                } else {
-                    end_of_statement(parser_info)?;
-                }
-            }
-
-            Ok(())
         } else {
-            return Err(Error::ExpectedStartingParantheses(parser_info.current_token_info.clone()));
+            Err(Error::ExpectedStartingParantheses(parser_info.current_token_info()))
         }
     } else if parser_info.match_token(Token::While) {
         bitwise(parser_info)?;
-        if !parser_info.match_token(Token::LeftBraces) {
-                return Err(Error::ExpectedStartingBrackets(parser_info.current_token_info.clone()));
+        match block_opener(parser_info) {
+            Some(closer) => block(parser_info, closer),
+            None => Err(Error::ExpectedStartingBrackets(parser_info.current_token_info()))
         }
-        while !parser_info.match_token(Token::RightBraces) {
-            if parser_info.match_token(Token::EOF) {
-                return Err(Error::MissingClosingBrackets(parser_info.current_token_info.clone()));
-            }
-            bitwise(parser_info)?;
-        }
-
-        Ok(())
     } else if parser_info.match_token(Token::LeftBraces) {
-        while !parser_info.match_token(Token::RightBraces) {
-            if parser_info.match_token(Token::EOF) {
-                return Err(Error::MissingClosingBrackets(parser_info.current_token_info.clone()));
-            }
-            bitwise(parser_info)?;
-        }
+        block(parser_info, Token::RightBraces)
+    } else if parser_info.options.permissive_console && parser_info.match_token(Token::Console) {
+        console_argument(parser_info)
+    } else if parser_info.match_token(Token::Vars) {
         Ok(())
-    } else if parser_info.match_token(Token::Console) {
-        bitwise(parser_info)
     } else {
-        return Err(Error::Generic(parser_info.current_token_info.clone(), parser_info.last_n_token_lexemes(3)));
+        Err(Error::Generic(parser_info.current_token_info(), parser_info.error_context()))
     }
 
 }
+
+/// Matches `begin` or `{` and returns the token that has to close the block.
+fn block_opener<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>) -> Option<Token> {
+    if parser_info.match_token(Token::Begin) {
+        Some(Token::End)
+    } else if parser_info.match_token(Token::LeftBraces) {
+        Some(Token::RightBraces)
+    } else {
+        None
+    }
+}
+
+/// Statements separated by semicolons up to `closer`; the last semicolon is optional.
+/// Expects the opener to have just been matched.
+fn block<S: Tokens + ?Sized>(parser_info: &mut ParserInfo<S>, closer: Token) -> Result<(), Error> {
+    let opener = parser_info.current;
+    // in_console stays set, so a block printed by CONSOLE can't assign either
+    let other_closer = if closer == Token::End { Token::RightBraces } else { Token::End };
+    let mismatched = |parser_info: &ParserInfo<S>| Error::MismatchedBlock(parser_info.current_token_info(), Box::new(parser_info.tokens.token_info(opener)));
+
+    while !parser_info.match_token(closer) {
+        if parser_info.match_token(Token::EOF) {
+            return Err(Error::MissingClosingBrackets(parser_info.current_token_info()));
+        } else if parser_info.match_token(other_closer) {
+            return Err(mismatched(parser_info));
+        }
+
+        statement(parser_info)?;
+        if parser_info.match_token(closer) {
+            break;
+        } else if parser_info.match_token(other_closer) {
+            return Err(mismatched(parser_info));
+        } else {
+            end_of_statement(parser_info)?;
+        }
+    }
+
+    Ok(())
+}
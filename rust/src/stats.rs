@@ -0,0 +1,73 @@
+use crate::tokenizer::{TokenInfo, Token};
+use std::collections::{HashMap, HashSet};
+
+/// Structural metrics of a token stream, cheap enough to compute for every submission.
+#[derive(Debug, Default)]
+pub struct TokenStats {
+    pub counts: HashMap<Token, usize>,
+    /// Top-level statements: one per `;` at nesting depth 0, plus a final statement
+    /// that is not followed by `;`.
+    pub statements: usize,
+    /// Deepest nesting of `( )`, `{ }` and `begin end`.
+    pub max_nesting: usize,
+    pub distinct_identifiers: usize,
+    /// Line of the last token.
+    pub lines: u32
+}
+
+pub fn stats(tokens: &[TokenInfo]) -> TokenStats {
+    let mut stats = TokenStats::default();
+    let mut identifiers = HashSet::new();
+    let mut depth: usize = 0;
+    let mut open_statement = false;
+
+    for token_info in tokens.iter().filter(|token_info| token_info.token != Token::EOF) {
+        *stats.counts.entry(token_info.token).or_insert(0) += 1;
+        stats.lines = token_info.start_position.row;
+
+        match token_info.token {
+            Token::LeftParantheses | Token::LeftBraces | Token::Begin => {
+                depth += 1;
+                stats.max_nesting = stats.max_nesting.max(depth);
+            },
+            Token::RightParantheses | Token::RightBraces | Token::End => depth = depth.saturating_sub(1),
+            Token::Identifier => {
+                identifiers.insert(token_info.lexeme.as_str());
+            },
+            _ => ()
+        }
+
+        if token_info.token == Token::Semicolon && depth == 0 {
+            stats.statements += 1;
+            open_statement = false;
+        } else if token_info.token != Token::Semicolon {
+            open_statement = true;
+        }
+    }
+
+    if open_statement {
+        stats.statements += 1;
+    }
+
+    stats.distinct_identifiers = identifiers.len();
+    stats
+}
+
+impl std::fmt::Display for TokenStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "statements: {}", self.statements)?;
+        writeln!(f, "max nesting: {}", self.max_nesting)?;
+        writeln!(f, "distinct identifiers: {}", self.distinct_identifiers)?;
+        writeln!(f, "lines: {}", self.lines)?;
+
+        let mut counts: Vec<(String, usize)> = self.counts.iter()
+            .map(|(token, count)| (token.to_string(), *count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        for (token, count) in counts {
+            writeln!(f, "{:<20} {}", token, count)?;
+        }
+
+        Ok(())
+    }
+}
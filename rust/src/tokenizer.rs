@@ -1,4 +1,6 @@
-use std::io::BufRead;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::sync::OnceLock;
 
 #[derive(Debug)]
 pub enum Error {
@@ -22,7 +24,8 @@ impl std::fmt::Display for Error {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
     None = 0,
     Multiplication,
@@ -43,6 +46,7 @@ pub enum Token {
     LowerThan,
     Comparison,
     Semicolon,
+    Comma,
     For,
     While,
     In,
@@ -51,13 +55,36 @@ pub enum Token {
     End,
     To,
     Console,
+    Vars,
     Ignore,
     EOT,
     EOF,
     Error
 }
 
-const MAX_STATE: usize = 31;
+const MAX_STATE: usize = 33;
+
+pub const RESERVED_WORDS: [(&str, Token); 8] = [
+    ("for", Token::For),
+    ("while", Token::While),
+    ("in", Token::In),
+    ("begin", Token::Begin),
+    ("end", Token::End),
+    ("to", Token::To),
+    ("CONSOLE", Token::Console),
+    ("VARS", Token::Vars)
+];
+
+impl Token {
+    pub fn is_reserved_word(self) -> bool {
+        RESERVED_WORDS.iter().any(|&(_, token)| token == self)
+    }
+
+    pub fn is_binary_operator(self) -> bool {
+        matches!(self, Token::Multiplication | Token::Division | Token::Addition | Token::Subtraction |
+            Token::BWAnd | Token::BWOr | Token::GreaterThan | Token::LowerThan | Token::Comparison)
+    }
+}
 
 impl From<u32> for Token {
     fn from(i: u32) -> Self {
@@ -81,18 +108,20 @@ impl From<u32> for Token {
             16 => Token::LowerThan,
             17 => Token::Comparison,
             18 => Token::Semicolon,
-            19 => Token::For,
-            20 => Token::While,
-            21 => Token::In,
-            22 => Token::Range,
-            23 => Token::Begin,
-            24 => Token::End,
-            25 => Token::To,
-            26 => Token::Console,
-            27 => Token::Ignore,
-            28 => Token::EOT,
-            29 => Token::EOF,
-            30 => Token::Error,
+            19 => Token::Comma,
+            20 => Token::For,
+            21 => Token::While,
+            22 => Token::In,
+            23 => Token::Range,
+            24 => Token::Begin,
+            25 => Token::End,
+            26 => Token::To,
+            27 => Token::Console,
+            28 => Token::Vars,
+            29 => Token::Ignore,
+            30 => Token::EOT,
+            31 => Token::EOF,
+            32 => Token::Error,
             _ => Token::None
         }
     }
@@ -120,6 +149,7 @@ impl std::fmt::Display for Token {
             Token::LowerThan => write!(f, "LOWER_THAN"),
             Token::Comparison => write!(f, "COMPARISON"),
             Token::Semicolon => write!(f, "SEMICOLON"),
+            Token::Comma => write!(f, "COMMA"),
             Token::For => write!(f, "FOR"),
             Token::While => write!(f, "WHILE"),
             Token::In => write!(f, "IN"),
@@ -128,6 +158,7 @@ impl std::fmt::Display for Token {
             Token::End => write!(f, "END"),
             Token::To => write!(f, "TO"),
             Token::Console => write!(f, "CONSOLE"),
+            Token::Vars => write!(f, "VARS"),
             Token::Ignore => write!(f, "IGNORE"),
             Token::EOT => write!(f, "EOT"),
             Token::EOF => write!(f, "EOF"),
@@ -136,88 +167,606 @@ impl std::fmt::Display for Token {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+impl std::str::FromStr for Token {
+    type Err = ();
+
+    /// Inverse of `Display`, so `"FOR".parse()` gives `Token::For`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        (0..MAX_STATE as u32).map(Token::from).find(|token| token.to_string() == name).ok_or(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     pub row: u32,
     pub col: u32
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TokenInfo {
     pub token: Token,
     pub lexeme: String,
+    pub start_position: Position,
+    /// Whitespace between the previous token and this one, kept only with
+    /// `Options::keep_trivia`. The `EOF` token holds whatever follows the last token.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leading_trivia: String
+}
+
+impl TokenInfo {
+    /// Position of the last character of the lexeme; no token spans more than one line.
+    pub fn end_position(&self) -> Position {
+        let len = self.lexeme.chars().count() as u32;
+        Position { row: self.start_position.row, col: self.start_position.col + len.saturating_sub(1) }
+    }
+
+    /// `line:startcol-endcol`, e.g. `3:5-7` for a three-character lexeme.
+    pub fn span(&self) -> String {
+        format!("{}:{}-{}", self.start_position.row, self.start_position.col, self.end_position().col)
+    }
+}
+
+/// A token without its lexeme, for checking syntax only; see `parser::parse_slim`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SlimToken {
+    pub token: Token,
     pub start_position: Position
 }
 
-struct DFA {
-    num_states: usize,
+/// What the parser and evaluator need from a token stream, so they work on `TokenInfo`s,
+/// `SlimToken`s and `CompactTokens` alike.
+pub(crate) trait Tokens {
+    fn len(&self) -> usize;
+    fn token(&self, i: usize) -> Token;
+    /// Empty for a `SlimToken`.
+    fn lexeme(&self, i: usize) -> &str;
+    fn start_position(&self, i: usize) -> Position;
+
+    /// Like `TokenInfo::end_position`. A `SlimToken` doesn't know its length, so this is only
+    /// right for one-character tokens there.
+    fn end_position(&self, i: usize) -> Position {
+        let start = self.start_position(i);
+        let len = self.lexeme(i).chars().count() as u32;
+        Position { row: start.row, col: start.col + len.saturating_sub(1) }
+    }
+
+    fn token_info(&self, i: usize) -> TokenInfo {
+        TokenInfo { token: self.token(i), lexeme: self.lexeme(i).to_string(), start_position: self.start_position(i), leading_trivia: String::new() }
+    }
+}
+
+impl Tokens for [TokenInfo] {
+    fn len(&self) -> usize {
+        <[TokenInfo]>::len(self)
+    }
+
+    fn token(&self, i: usize) -> Token {
+        self[i].token
+    }
+
+    fn lexeme(&self, i: usize) -> &str {
+        &self[i].lexeme
+    }
+
+    fn start_position(&self, i: usize) -> Position {
+        self[i].start_position
+    }
+
+    fn token_info(&self, i: usize) -> TokenInfo {
+        self[i].clone()
+    }
+}
+
+impl Tokens for [SlimToken] {
+    fn len(&self) -> usize {
+        <[SlimToken]>::len(self)
+    }
+
+    fn token(&self, i: usize) -> Token {
+        self[i].token
+    }
+
+    fn lexeme(&self, _: usize) -> &str {
+        ""
+    }
+
+    fn start_position(&self, i: usize) -> Position {
+        self[i].start_position
+    }
+}
+
+impl Tokens for CompactTokens {
+    fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    fn token(&self, i: usize) -> Token {
+        self.tokens[i].token
+    }
+
+    fn lexeme(&self, i: usize) -> &str {
+        self.tokens[i].lexeme(&self.text)
+    }
+
+    fn start_position(&self, i: usize) -> Position {
+        self.tokens[i].start_position
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Options {
+    /// Match reserved words regardless of case, so `FOR`, `For` and `for` are all `Token::For`.
+    pub case_insensitive_keywords: bool,
+    /// Emit a `Token::Error` for an unrecognized character and carry on with the next one
+    /// instead of failing, so incomplete code can still be tokenized.
+    pub recover_invalid_characters: bool,
+    /// Fill in `TokenInfo::leading_trivia`, so `reconstruct` can give back the exact source.
+    pub keep_trivia: bool,
+    pub strictness: Strictness
+}
+
+impl Options {
+    fn effective(mut self) -> Self {
+        if self.strictness == Strictness::Strict {
+            self.case_insensitive_keywords = false;
+            self.recover_invalid_characters = false;
+        }
+        self
+    }
+}
+
+/// Whether the lenient behaviours kept for older course material are allowed. Set the same
+/// value on `tokenizer::Options`, `parser::Options` and `eval::Options`; under `Strict`:
+///
+/// - the tokenizer matches reserved words case-sensitively and fails on the first invalid
+///   character, ignoring `case_insensitive_keywords` and `recover_invalid_characters`,
+/// - the parser and evaluator only accept CONSOLE as a statement printing a pure expression,
+///   ignoring `permissive_console`, and only accept an assignment as a statement of its own,
+///   so `c := (d := 5) + 1` and `a := b := 5` are rejected,
+/// - the evaluator rejects top-level statements whose value is unused, as with
+///   `reject_unused_values`,
+/// - a for loop's control variable only exists inside the loop; afterwards it has its value
+///   from before the loop again, or is undefined if it had none.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Strictness {
+    #[default]
+    Legacy,
+    Strict
+}
+
+struct Dfa {
     alphabet: [char; 256],
-    last: char,
+    transitions_table: &'static [Vec<u32>],
+    /// The character that ended the previous token, to be read again as the start of the next.
+    last: Option<char>,
+    last_position: Position,
     final_states: Vec<Token>,
-    position: Position
-}
-
-pub fn tokenize<R: BufRead>(mut tokens_reader: R) -> Result<Vec<TokenInfo>, Error> {
-    let mut dfa = DFA {
-        num_states: MAX_STATE,
-        alphabet: [char::default(); 256],
-        last: char::default(),
-        final_states: vec![Token::Int, Token::Hex, Token::End, Token::Multiplication,
-            Token::Division, Token::Addition, Token::Subtraction, Token::EOF,
-            Token::Identifier, Token::None, Token::LeftParantheses, Token::RightParantheses,
-            Token::LeftBraces, Token::RightBraces, Token::Assignment, Token::Semicolon,
-            Token::For, Token::While, Token::Begin, Token::To, Token::Console, Token::Ignore, Token::BWAnd, Token::BWOr, Token::Range, Token::In, Token::GreaterThan, Token::LowerThan, Token::Comparison],
-        position: Position { row: 1, col: 1 }
-    };
+    position: Position,
+    options: Options,
+    /// Every character consumed and the state it led to, when tracing.
+    trace: Option<Vec<(char, Token)>>,
+    /// Leave the lexeme empty except for identifiers, which still need it to pick out
+    /// reserved words. Only for `tokenize_slim`, which drops lexemes anyway.
+    identifier_lexemes_only: bool
+}
 
-    let mut vec = Vec::new();
+impl Dfa {
+    fn new(options: Options, position: Position) -> Self {
+        let mut dfa = Dfa {
+            alphabet: [char::default(); 256],
+            transitions_table: transitions_table(),
+            last: None,
+            last_position: position,
+            final_states: vec![Token::Int, Token::Hex, Token::End, Token::Multiplication,
+                Token::Division, Token::Addition, Token::Subtraction, Token::EOF,
+                Token::Identifier, Token::None, Token::LeftParantheses, Token::RightParantheses,
+                Token::LeftBraces, Token::RightBraces, Token::Assignment, Token::Semicolon, Token::Comma,
+                Token::For, Token::While, Token::Begin, Token::To, Token::Console, Token::Ignore, Token::BWAnd, Token::BWOr, Token::Range, Token::In, Token::GreaterThan, Token::LowerThan, Token::Comparison],
+            position,
+            options: options.effective(),
+            trace: None,
+            identifier_lexemes_only: false
+        };
+
+        for i in 0..=255 {
+            dfa.alphabet[i] = char::from_u32(i as u32).unwrap();
+        }
 
-    for i in 0..=255 {
-        dfa.alphabet[i] = char::from_u32(i as u32).unwrap();
+        dfa
     }
+}
 
-    match get_token(&mut tokens_reader, &mut dfa) {
-        Ok(mut token_info) => {
-            while token_info.token != Token::EOF {
-                if token_info.token != Token::None {
-                    vec.push(token_info);
-                }
+pub fn tokenize<R: BufRead>(tokens_reader: R) -> Result<Vec<TokenInfo>, Error> {
+    tokenize_with_options(tokens_reader, Options::default())
+}
 
-                token_info = match get_token(&mut tokens_reader, &mut dfa) {
-                    Ok(token_info) => token_info,
-                    Err(error) => return Err(error)
-                }
-            }
-        },
+pub fn tokenize_with_options<R: BufRead>(tokens_reader: R, options: Options) -> Result<Vec<TokenInfo>, Error> {
+    tokenize_from(tokens_reader, options, Position { row: 1, col: 1 })
+}
 
-        Err(error) => return Err(error)
-    };
+/// Runs the DFA over `input` and records each character it consumes with the state it moved
+/// to, e.g. `1` to `Int`. Whitespace moves to `EOT`; reserved words are only picked out after
+/// the DFA, so `to` shows up as `Identifier` states. The character that ends a token is
+/// consumed again as the start of the next one, so it only appears once. Stops at the first
+/// character no token can start with.
+pub fn trace(input: &str) -> Vec<(char, Token)> {
+    let mut dfa = Dfa::new(Options::default(), Position { row: 1, col: 1 });
+    dfa.trace = Some(Vec::new());
+    let mut reader = input.as_bytes();
+    while let Ok(token_info) = get_token(&mut reader, &mut dfa) {
+        if token_info.token == Token::EOF {
+            break;
+        }
+    }
+
+    dfa.trace.unwrap_or_default()
+}
+
+/// Like `tokenize_with_options`, but keeps only each token's kind and position. Trivia is
+/// never kept.
+pub fn tokenize_slim<R: BufRead>(tokens_reader: R, options: Options) -> Result<Vec<SlimToken>, Error> {
+    let mut vec = Vec::new();
+    let mut dfa = Dfa::new(Options { keep_trivia: false, ..options }, Position { row: 1, col: 1 });
+    dfa.identifier_lexemes_only = true;
+    for_each_token(tokens_reader, dfa, |token_info| {
+        vec.push(SlimToken { token: token_info.token, start_position: token_info.start_position });
+    })?;
+    Ok(vec)
+}
+
+fn tokenize_from<R: BufRead>(tokens_reader: R, options: Options, position: Position) -> Result<Vec<TokenInfo>, Error> {
+    let mut vec = Vec::new();
+    for_each_token(tokens_reader, Dfa::new(options, position), |token_info| vec.push(token_info))?;
+    Ok(vec)
+}
+
+fn for_each_token<R: BufRead, F: FnMut(TokenInfo)>(mut tokens_reader: R, mut dfa: Dfa, mut f: F) -> Result<(), Error> {
 
-    vec.push(TokenInfo {
+    let mut trivia = String::new();
+    let mut token_info = get_token(&mut tokens_reader, &mut dfa)?;
+    while token_info.token != Token::EOF {
+        if token_info.token == Token::None {
+            trivia.push_str(&token_info.lexeme);
+        } else {
+            token_info.leading_trivia = std::mem::take(&mut trivia);
+            f(token_info);
+        }
+
+        token_info = get_token(&mut tokens_reader, &mut dfa)?;
+    }
+
+    f(TokenInfo {
         token: Token::EOF,
         lexeme: String::from(""),
-        start_position: dfa.position
+        start_position: dfa.position,
+        leading_trivia: trivia
     });
 
-    Ok(vec)
+    Ok(())
+}
+
+/// A token of `CompactTokens`. Instead of owning its lexeme it points into the text buffer
+/// they share, which takes a quarter of the memory of a `TokenInfo` for large inputs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CompactToken {
+    pub token: Token,
+    pub start_position: Position,
+    offset: u32,
+    len: u16
 }
 
-fn get_token<R: BufRead>(mut tokens_reader: R, mut dfa: &mut DFA) -> Result<TokenInfo, Error>
+impl CompactToken {
+    /// The lexeme, given the `CompactTokens::text` this token came from.
+    pub fn lexeme<'src>(&self, src: &'src str) -> &'src str {
+        &src[self.offset as usize..self.offset as usize + self.len as usize]
+    }
+}
+
+/// Tokens whose lexemes are stored one after another in a single buffer. Trivia is not kept.
+#[derive(Debug, Clone, Default)]
+pub struct CompactTokens {
+    text: String,
+    tokens: Vec<CompactToken>
+}
+
+impl CompactTokens {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn tokens(&self) -> &[CompactToken] {
+        &self.tokens
+    }
+
+    /// Fails with `Error::InvalidPattern` for a lexeme longer than 65535 bytes or once the
+    /// text would pass 4 GiB.
+    fn push(&mut self, token_info: &TokenInfo) -> Result<(), Error> {
+        let too_long = || Error::InvalidPattern(token_info.lexeme.clone(), token_info.start_position);
+        let offset = u32::try_from(self.text.len()).map_err(|_| too_long())?;
+        let len = u16::try_from(token_info.lexeme.len()).map_err(|_| too_long())?;
+        offset.checked_add(len.into()).ok_or_else(too_long)?;
+
+        self.text.push_str(&token_info.lexeme);
+        self.tokens.push(CompactToken { token: token_info.token, start_position: token_info.start_position, offset, len });
+        Ok(())
+    }
+
+    /// The same tokens as `TokenInfo`s. `parser::parse_compact` and `eval::run_compact` work on
+    /// the compact form directly.
+    pub fn to_token_infos(&self) -> Vec<TokenInfo> {
+        self.tokens.iter()
+            .map(|compact| TokenInfo {
+                token: compact.token,
+                lexeme: compact.lexeme(&self.text).to_string(),
+                start_position: compact.start_position,
+                leading_trivia: String::new()
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&[TokenInfo]> for CompactTokens {
+    type Error = Error;
+
+    fn try_from(tokens: &[TokenInfo]) -> Result<Self, Error> {
+        let mut compact = CompactTokens::default();
+        for token_info in tokens {
+            compact.push(token_info)?;
+        }
+        Ok(compact)
+    }
+}
+
+/// Like `tokenize_with_options`, but never holds more than one `TokenInfo` at a time.
+pub fn tokenize_compact<R: BufRead>(tokens_reader: R, options: Options) -> Result<CompactTokens, Error> {
+    let mut compact = CompactTokens::default();
+    let mut result = Ok(());
+    for_each_token(tokens_reader, Dfa::new(Options { keep_trivia: false, ..options }, Position { row: 1, col: 1 }), |token_info| {
+        if result.is_ok() {
+            result = compact.push(&token_info);
+        }
+    })?;
+    result.map(|()| compact)
+}
+
+/// Gives back the source of tokens produced with `Options::keep_trivia`, byte for byte.
+pub fn reconstruct(tokens: &[TokenInfo]) -> String {
+    tokens.iter()
+        .flat_map(|token_info| [token_info.leading_trivia.as_str(), token_info.lexeme.as_str()])
+        .collect()
+}
+
+/// A replacement of the bytes in `range` of the old source with `new_text`.
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub new_text: String
+}
+
+/// Produces the same tokens as `tokenize_with_options(new_src, options)`, but only re-lexes
+/// the lines touched by `edit`. No token spans a line break, so tokens on lines before the edit
+/// are reused as they are and tokens on lines after it are reused with their rows shifted.
+/// `old_tokens` have to come from `old_src` with the same `options`.
+pub fn retokenize(old_tokens: &[TokenInfo], old_src: &str, edit: TextEdit, new_src: &str, options: Options) -> Result<Vec<TokenInfo>, Error> {
+    let before_edit = old_src.get(..edit.range.start).ok_or(Error::InvalidStream)?;
+    let old_edited = old_src.get(..edit.range.end).ok_or(Error::InvalidStream)?;
+    let new_end = edit.range.start + edit.new_text.len();
+    let new_edited = new_src.get(..new_end).ok_or(Error::InvalidStream)?;
+
+    let edit_row = before_edit.matches('\n').count() as u32 + 1;
+    let old_end_row = old_edited.matches('\n').count() as u32 + 1;
+    let new_end_row = new_edited.matches('\n').count() as u32 + 1;
+    let line_start = before_edit.rfind('\n').map_or(0, |i| i + 1);
+
+    let kept = old_tokens.iter()
+        .take_while(|token_info| token_info.token != Token::EOF && token_info.start_position.row < edit_row)
+        .count();
+    let mut tokens = old_tokens[..kept].to_vec();
+
+    let line_end = new_src[new_end..].find('\n').map(|i| new_end + i + 1);
+    let relex_end = line_end.unwrap_or(new_src.len());
+    let mut relexed = tokenize_from(&new_src.as_bytes()[line_start..relex_end], options, Position { row: edit_row, col: 1 })?;
+
+    // whitespace between the last reused token and the edited lines
+    let next = old_tokens.get(kept).ok_or(Error::InvalidStream)?;
+    let (before_lines, _) = split_trivia(next, edit_row);
+    relexed[0].leading_trivia.insert_str(0, before_lines);
+
+    if line_end.is_none() {
+        tokens.append(&mut relexed);
+        return Ok(tokens);
+    }
+
+    let trailing = relexed.pop().map(|eof| eof.leading_trivia).unwrap_or_default();
+    tokens.append(&mut relexed);
+    let first_reused = tokens.len();
+
+    let shift = new_end_row as i64 - old_end_row as i64;
+    let reused = old_tokens.iter().position(|token_info| token_info.start_position.row > old_end_row).ok_or(Error::InvalidStream)?;
+    tokens.extend(old_tokens[reused..].iter().map(|token_info| {
+        let mut token_info = token_info.clone();
+        token_info.start_position.row = (token_info.start_position.row as i64 + shift) as u32;
+        token_info
+    }));
+
+    // whitespace on the edited lines was re-lexed already
+    let after_lines = split_trivia(&old_tokens[reused], old_end_row + 1).1;
+    tokens[first_reused].leading_trivia = trailing + after_lines;
+    Ok(tokens)
+}
+
+/// Splits the leading trivia of `token_info` where row `row` starts.
+fn split_trivia(token_info: &TokenInfo, row: u32) -> (&str, &str) {
+    let trivia = token_info.leading_trivia.as_str();
+    let first_row = (token_info.start_position.row as usize).saturating_sub(trivia.matches('\n').count());
+    let split = match (row as usize).saturating_sub(first_row) {
+        0 => 0,
+        newlines => trivia.match_indices('\n').nth(newlines - 1).map_or(trivia.len(), |(i, _)| i + 1)
+    };
+    trivia.split_at(split)
+}
+
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    UnsupportedVersion(usize, String),
+    Malformed(usize, String),
+    MissingEOF(usize)
+}
+
+impl std::error::Error for ReplayError {}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(error) =>
+                write!(f, "Replay error: cannot read stream: {}", error),
+            ReplayError::UnsupportedVersion(line, header) =>
+                write!(f, "Replay error: expected header 'tokens v{}', found '{}' on line {}", REPLAY_VERSION, header, line),
+            ReplayError::Malformed(line, reason) =>
+                write!(f, "Replay error: {} on line {}", reason, line),
+            ReplayError::MissingEOF(line) =>
+                write!(f, "Replay error: token stream does not end with a single EOF on line {}", line)
+        }
+    }
+}
+
+/// Writes tokens in the replay format: a `tokens v1` header, then one token per line as
+/// `row col NAME "lexeme"`, with `\\`, `\"`, `\n`, `\r`, `\t` and `\u{..}` escapes in the lexeme.
+pub fn write_replay<W: Write>(mut writer: W, tokens: &[TokenInfo]) -> std::io::Result<()> {
+    writeln!(writer, "tokens v{}", REPLAY_VERSION)?;
+    for token_info in tokens {
+        write!(writer, "{} {} {} ", token_info.start_position.row, token_info.start_position.col, token_info.token)?;
+        write_quoted(&mut writer, &token_info.lexeme)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `text` in double quotes with `\\`, `\"`, `\n`, `\r`, `\t` and `\u{..}` escapes, so it
+/// stays on one line.
+pub(crate) fn write_quoted<W: Write>(mut writer: W, text: &str) -> std::io::Result<()> {
+    write!(writer, "\"")?;
+    for c in text.chars() {
+        match c {
+            '\\' => write!(writer, "\\\\")?,
+            '"' => write!(writer, "\\\"")?,
+            '\n' => write!(writer, "\\n")?,
+            '\r' => write!(writer, "\\r")?,
+            '\t' => write!(writer, "\\t")?,
+            c if c.is_ascii_graphic() || c == ' ' => write!(writer, "{}", c)?,
+            c => write!(writer, "\\u{{{:x}}}", c as u32)?
+        }
+    }
+    write!(writer, "\"")
+}
+
+/// Reads tokens written by `write_replay`. The stream must end with its single `EOF` token,
+/// since the parser and eval rely on it.
+pub fn read_replay<R: BufRead>(reader: R) -> Result<Vec<TokenInfo>, ReplayError> {
+    let mut lines = reader.lines();
+    let header = lines.next().transpose().map_err(ReplayError::Io)?.unwrap_or_default();
+    if header != format!("tokens v{}", REPLAY_VERSION) {
+        return Err(ReplayError::UnsupportedVersion(1, header));
+    }
+
+    let mut tokens: Vec<TokenInfo> = Vec::new();
+    let mut line_number = 1;
+    for line in lines {
+        let line = line.map_err(ReplayError::Io)?;
+        line_number += 1;
+        if tokens.last().is_some_and(|token_info| token_info.token == Token::EOF) {
+            return Err(ReplayError::MissingEOF(line_number));
+        }
+
+        tokens.push(read_replay_line(&line).map_err(|reason| ReplayError::Malformed(line_number, reason))?);
+    }
+
+    match tokens.last() {
+        Some(token_info) if token_info.token == Token::EOF => Ok(tokens),
+        _ => Err(ReplayError::MissingEOF(line_number))
+    }
+}
+
+fn read_replay_line(line: &str) -> Result<TokenInfo, String> {
+    let mut fields = line.splitn(4, ' ');
+    let mut next_field = |name: &str| fields.next().ok_or(format!("missing {}", name));
+    let row = next_field("row")?.parse().map_err(|_| String::from("invalid row"))?;
+    let col = next_field("column")?.parse().map_err(|_| String::from("invalid column"))?;
+    let name = next_field("token name")?;
+    let token = name.parse().map_err(|_| format!("unknown token '{}'", name))?;
+    let quoted = next_field("lexeme")?;
+
+    let lexeme = read_quoted(quoted, "lexeme")?;
+
+    Ok(TokenInfo { token, lexeme, start_position: Position { row, col }, leading_trivia: String::new() })
+}
+
+/// Reads text written by `write_quoted`; `what` names it in error messages.
+pub(crate) fn read_quoted(quoted: &str, what: &str) -> Result<String, String> {
+    let inner = quoted.strip_prefix('"').and_then(|quoted| quoted.strip_suffix('"'))
+        .ok_or(format!("{} is not quoted", what))?;
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            return Err(format!("unescaped quote in {}", what));
+        }
+
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('\\') => text.push('\\'),
+            Some('"') => text.push('"'),
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some('t') => text.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                text.push(code.strip_prefix('{')
+                    .and_then(|code| u32::from_str_radix(code, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or(format!("invalid \\u escape in {}", what))?);
+            },
+            _ => return Err(format!("invalid escape in {}", what))
+        }
+    }
+
+    Ok(text)
+}
+
+/// Reads the next token. Besides real tokens this returns exactly two other kinds:
+/// - `Token::None` for one whitespace character, with that character as the lexeme when
+///   `keep_trivia` is on and an empty lexeme otherwise. Every call consumes input, so there is
+///   no such thing as an empty read.
+/// - `Token::EOF` once the input is used up, and on every call after that.
+///
+/// Any other character no token can start with, NUL and the other control bytes included, is
+/// an `InvalidPattern`, or a `Token::Error` with `recover_invalid_characters`.
+fn get_token<R: BufRead>(mut tokens_reader: R, dfa: &mut Dfa) -> Result<TokenInfo, Error>
 {
-    let transitions_table = create_transitions_table(dfa.alphabet.len(), dfa.num_states);
     let mut buffer = [0; 1];
     let mut token_info = TokenInfo {
         token: Token::None,
         lexeme: String::from(""),
-        start_position: dfa.position
+        start_position: dfa.position,
+        leading_trivia: String::new()
     };
 
     let mut state = Token::None;
     let mut code: char;
 
-    if dfa.last != char::default() {
-        code = dfa.last;
-        dfa.last = char::default();
+    if let Some(last) = dfa.last.take() {
+        code = last;
+        token_info.start_position = dfa.last_position;
     }
     else {
         if tokens_reader.read(&mut buffer).unwrap() > 0 {
@@ -243,13 +792,25 @@ fn get_token<R: BufRead>(mut tokens_reader: R, mut dfa: &mut DFA) -> Result<Toke
     }*/
 
     loop {
-        let next_state = transitions_table[state as usize][code as usize].into();
+        let next_state = dfa.transitions_table[state as usize][code as usize].into();
         if next_state == Token::EOT || next_state == Token::EOF {
+            // whitespace comes one character at a time as a Token::None
+            if state == Token::None && dfa.options.keep_trivia {
+                token_info.lexeme.push(code);
+            }
+            if let (Some(trace), Token::None, Token::EOT) = (&mut dfa.trace, state, next_state) {
+                trace.push((code, next_state));
+            }
             break;
         }
 
-        if state == Token::None && next_state == Token::None && code != char::default() {
+        if state == Token::None && next_state == Token::None {
             token_info.lexeme.push(code);
+            if dfa.options.recover_invalid_characters {
+                token_info.token = Token::Error;
+                return Ok(token_info);
+            }
+
             return Err(Error::InvalidPattern(token_info.lexeme, token_info.start_position));
         }
 
@@ -258,45 +819,64 @@ fn get_token<R: BufRead>(mut tokens_reader: R, mut dfa: &mut DFA) -> Result<Toke
         }
 
         state = next_state;
-        token_info.lexeme.push(code);
+        if !dfa.identifier_lexemes_only || state == Token::Identifier {
+            token_info.lexeme.push(code);
+        }
+        if let Some(trace) = &mut dfa.trace {
+            trace.push((code, state));
+        }
 
         if tokens_reader.read(&mut buffer).unwrap() > 0 {
             code = buffer[0] as char;
-            dfa.last = code;
+            dfa.last = Some(code);
+            dfa.last_position = dfa.position;
             dfa.position = update_position(dfa.position, code);
         } else {
-            token_info.token = Token::EOF;
-            return Ok(token_info);
+            // the input ended right after this token; finish it and report EOF on the next call
+            dfa.last = None;
+            break;
         }
-        
     }
 
-    if dfa.final_states.contains(&state.into()) {
-        token_info.token = state.into();
-        token_info.token = assign_if_reserved_identifier(&token_info);
-        if token_info.token == Token::None {
-            Ok(token_info)
-        } else {
-            token_info.start_position.row = dfa.position.row;
-            token_info.start_position.col = dfa.position.col;
-            Ok(token_info)
-        }
+    // the DFA should never accept anything else, but an identifier is what ends up in the
+    // variables map, so don't take its word for it
+    if state == Token::Identifier && !is_identifier(&token_info.lexeme) {
+        return Err(Error::InvalidPattern(token_info.lexeme, token_info.start_position));
+    }
+
+    if dfa.final_states.contains(&state) {
+        token_info.token = state;
+        token_info.token = assign_if_reserved_identifier(&token_info, dfa.options.case_insensitive_keywords);
+        Ok(token_info)
     } else {
         Err(Error::InvalidPattern(token_info.lexeme, token_info.start_position))
     }
 }
 
-fn assign_if_reserved_identifier(token_info: &TokenInfo) -> Token {
-    match token_info.lexeme.as_ref() {
-        "for" => Token::For,
-        "while" => Token::While,
-        "in" => Token::In,
-        "begin" => Token::Begin,
-        "end" => Token::End,
-        "to" => Token::To,
-        "CONSOLE" => Token::Console,
-        _ => token_info.token
+/// `[A-Za-z][A-Za-z0-9]*`
+fn is_identifier(lexeme: &str) -> bool {
+    let mut chars = lexeme.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic()) && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+fn assign_if_reserved_identifier(token_info: &TokenInfo, case_insensitive: bool) -> Token {
+    if token_info.token != Token::Identifier {
+        return token_info.token;
     }
+
+    RESERVED_WORDS.iter()
+        .find(|(word, _)| if case_insensitive {
+            word.eq_ignore_ascii_case(&token_info.lexeme)
+        } else {
+            *word == token_info.lexeme
+        })
+        .map_or(token_info.token, |&(_, token)| token)
+}
+
+/// Built on first use and shared by every tokenizer run after that.
+fn transitions_table() -> &'static [Vec<u32>] {
+    static TABLE: OnceLock<Vec<Vec<u32>>> = OnceLock::new();
+    TABLE.get_or_init(|| create_transitions_table(256, MAX_STATE))
 }
 
 fn create_transitions_table(alphabet_len: usize, num_states: usize) -> Vec<Vec<u32>> {
@@ -307,6 +887,7 @@ fn create_transitions_table(alphabet_len: usize, num_states: usize) -> Vec<Vec<u
     };
 
     set_transition(Token::None, ';', Token::Semicolon);
+    set_transition(Token::None, ',', Token::Comma);
     set_transition(Token::None, ':', Token::Assignment);
     set_transition(Token::Assignment, '=', Token::Assignment);
 
@@ -364,7 +945,8 @@ fn create_transitions_table(alphabet_len: usize, num_states: usize) -> Vec<Vec<u
     set_transition(Token::None, '.', Token::Range);
     set_transition(Token::Range, '.', Token::Range);
 
-    set_transition(Token::None, Token::EOF as u8 as char, Token::EOF);
+    // there is deliberately no transition to Token::EOF: the end of input is noticed when a
+    // read comes back empty, so byte 31 (Token::EOF as u8) is as invalid as NUL
     transitions_table
 }
 
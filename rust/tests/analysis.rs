@@ -0,0 +1,31 @@
+use rust::analysis::{constant_while_conditions, ConstantCondition};
+use rust::tokenizer::{tokenize, Position};
+
+fn constant_conditions(program: &str) -> Vec<ConstantCondition> {
+    constant_while_conditions(&tokenize(program.as_bytes()).unwrap())
+}
+
+#[test]
+fn literal_while_conditions_are_reported() {
+    let found = constant_conditions("a := 0;\nwhile 1 {\n    a := a + 1\n}\n");
+    assert_eq!(found, [ConstantCondition { position: Position { row: 2, col: 7 }, value: 1 }]);
+    assert_eq!(found[0].to_string(), "Warning: while condition on line 2 is always true, so the loop never ends (it is always 1)");
+
+    let found = constant_conditions("while 0 begin\n    CONSOLE 1\nend\n");
+    assert!(!found[0].always_true());
+    assert_eq!(found[0].to_string(), "Warning: while condition on line 1 is never true, so the loop body never runs (it is always 0)");
+}
+
+#[test]
+fn folded_while_conditions_are_reported() {
+    assert_eq!(constant_conditions("while (3 * 4) > 10 { CONSOLE 1 }\n")[0].value, 1);
+    assert_eq!(constant_conditions("while #F - 15 { CONSOLE 1 }\n")[0].value, 0);
+    assert!(constant_conditions("while 1 / 0 { CONSOLE 1 }\n").is_empty());
+}
+
+#[test]
+fn conditions_with_identifiers_are_not_constant() {
+    assert!(constant_conditions("n := 3;\nwhile n > 0 { n := n - 1 }\n").is_empty());
+    assert!(constant_conditions("n := 3;\nwhile 1 & n { n := n - 1 }\n").is_empty());
+    assert!(constant_conditions("while abs(0) { CONSOLE 1 }\n").is_empty());
+}
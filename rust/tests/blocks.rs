@@ -0,0 +1,114 @@
+use rust::{eval, parser, run_str};
+use rust::tokenizer::tokenize;
+use std::collections::HashMap;
+
+fn run(program: &str) -> HashMap<String, i64> {
+    let mut variables = HashMap::new();
+    run_str(program, &mut variables).unwrap();
+    variables
+}
+
+#[test]
+fn while_runs_with_either_block_form() {
+    for program in [
+        "i := 0; s := 0;\nwhile i < 5 {\n    i := i + 1;\n    s := s + i\n}\n",
+        "i := 0; s := 0;\nwhile i < 5 begin\n    i := i + 1;\n    s := s + i\nend\n"
+    ] {
+        let variables = run(program);
+        assert_eq!(variables["i"], 5);
+        assert_eq!(variables["s"], 15);
+    }
+}
+
+#[test]
+fn for_runs_with_either_block_form() {
+    for program in [
+        "s := 0;\nfor (i := 1 to 4) {\n    s := s + i * i\n}\n",
+        "s := 0;\nfor (i := 1 to 4) begin\n    s := s + i * i\nend\n"
+    ] {
+        assert_eq!(run(program)["s"], 30);
+    }
+}
+
+#[test]
+fn loops_that_never_run_skip_their_body() {
+    let variables = run("s := 7;\nwhile 0 { s := 1 };\nfor (i := 5 to 1) begin s := 2 end;\nt := s\n");
+    assert_eq!(variables["t"], 7);
+}
+
+#[test]
+fn comparisons_yield_one_or_zero() {
+    let variables = run("a := 3 < 4; b := 3 > 4; c := 2 == 2\n");
+    assert_eq!((variables["a"], variables["b"], variables["c"]), (1, 0, 1));
+}
+
+#[test]
+fn mismatched_block_forms_are_reported_where_they_close() {
+    for header in ["for (i := 1 to 2)", "while 0"] {
+        for (opener, closer, matches) in [("{", "}", true), ("begin", "end", true), ("{", "end", false), ("begin", "}", false)] {
+            let program = format!("a := 1;\n{} {}\n    a := 2\n{}\n", header, opener, closer);
+            let tokens = tokenize(program.as_bytes()).unwrap();
+
+            let parsed = parser::parse(&tokens, parser::DEFAULT_MAX_DEPTH);
+            let evaluated = eval::parse(&tokens, &mut HashMap::new());
+            if matches {
+                assert!(parsed.is_ok() && evaluated.is_ok(), "{}", program);
+                continue;
+            }
+
+            let expected = format!("Syntax error: found '{}' on line 4 but block was opened with '{}' on line 2", closer, opener);
+            assert_eq!(parsed.unwrap_err().to_string(), expected);
+            // a while loop that never runs skips its body without looking inside
+            if header.starts_with("for") {
+                let error = evaluated.unwrap_err();
+                assert!(matches!(error, eval::Error::MismatchedBlock(_, _)));
+                assert_eq!(error.token_info().lexeme, closer);
+            }
+        }
+    }
+}
+
+#[test]
+fn assignments_in_a_for_body_accumulate_across_iterations() {
+    let variables = run("s := 0;\nfor(i:=1 to 5) begin s := s + i end\n");
+    assert_eq!(variables["s"], 15);
+
+    let variables = run("sum := 0; count := 0;\nfor (i := 2 to 6) {\n    sum := sum + i;\n    count := count + 1\n};\nmean := sum / count\n");
+    assert_eq!((variables["sum"], variables["count"], variables["mean"]), (20, 5, 4));
+}
+
+#[test]
+fn every_statement_of_a_for_body_runs_each_iteration() {
+    let variables = run("s := 0; t := 1;\nfor (i := 1 to 4) begin s := s + i; t := t * 2 end\n");
+    assert_eq!((variables["s"], variables["t"]), (10, 16));
+
+    let variables = run("s := 0; t := 1;\nfor (i := 1 to 4) {\n    s := s + i;\n    t := t * 2;\n}\n");
+    assert_eq!((variables["s"], variables["t"]), (10, 16));
+}
+
+#[test]
+fn nested_loops_compute_a_multiplication_table() {
+    let variables = run("s := 0;\nfor (i := 1 to 9) begin\n    for (j := 1 to 9) begin\n        s := s + i * j\n    end\nend\n");
+    assert_eq!(variables["s"], 2025);
+}
+
+#[test]
+fn inner_loops_do_not_change_an_outer_control_variable() {
+    let variables = run("s := 0; c := 0;\nfor (i := 1 to 3) begin\n    for (i := 10 to 11) begin c := c + 1 end;\n    s := s + i\nend\n");
+    assert_eq!((variables["s"], variables["c"]), (6, 6));
+    // outside any loop the control variable keeps its last value as before
+    assert_eq!(variables["i"], 3);
+}
+
+#[test]
+fn brace_blocks_are_worth_their_last_statement() {
+    let variables = run("x := { 1; 2; 3 };\ny := { a := 4; a * 2; };\nz := {} + 1\n");
+
+    assert_eq!((variables["x"], variables["y"], variables["z"]), (3, 8, 1));
+    assert_eq!(variables["a"], 4);
+}
+
+#[test]
+fn loops_in_a_block_are_worth_zero() {
+    assert_eq!(run("x := { 5; for (i := 1 to 3) begin i end }\n")["x"], 0);
+}
@@ -0,0 +1,183 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A file name no other test, in this process or another, is using at the same time.
+fn temp_path(kind: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("cli-{}-{}-{}.txt", std::process::id(), n, kind))
+}
+
+fn run(args: &[&str], program: &str) -> Output {
+    let path = temp_path("program");
+    std::fs::File::create(&path).unwrap().write_all(program.as_bytes()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rust")).args(args).arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+#[test]
+fn max_steps_stops_an_infinite_loop() {
+    let output = run(&["--max-steps", "1000"], "i := 0;\nwhile 1 {\n    i := i + 1\n}\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Evaluation error: step limit exceeded"));
+}
+
+#[test]
+fn max_depth_limits_nesting() {
+    let output = run(&["--max-depth", "3"], "a := ((((1))))\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Syntax error: expression nested too deeply"));
+}
+
+#[test]
+fn max_depth_also_limits_evaluation() {
+    let program = format!("a := {}1{}\n", "(".repeat(200), ")".repeat(200));
+
+    let output = run(&["--max-depth", "250"], &program);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stdout));
+    let output = run(&[], &program);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Syntax error: expression nested too deeply"));
+    let output = run(&["--max-depth", "250", "--max-expr-depth", "150"], &program);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Evaluation error: expression nested too deeply"));
+}
+
+#[test]
+fn depths_the_stack_cannot_take_are_a_usage_error() {
+    for flag in ["--max-depth", "--max-expr-depth"] {
+        let output = run(&[flag, "200000"], "a := 1\n");
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with(&format!("Usage: {} expects a number no greater than 256", flag)));
+    }
+}
+
+#[test]
+fn invalid_numbers_are_a_usage_error() {
+    let output = run(&["--max-steps", "lots"], "a := 1\n");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Usage: --max-steps"));
+}
+
+#[test]
+fn clean_programs_exit_successfully() {
+    let output = run(&["--max-steps", "1000"], "CONSOLE 1 + 2\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn help_lists_the_options() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust")).arg("--help").output().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Usage: rust [OPTIONS] FILE..."));
+    for flag in ["--stats", "--check", "--collect", "--warn-unused", "--coverage", "--scenarios", "--reject-unused-values", "--max-steps", "--max-depth", "--strict", "--version"] {
+        assert!(stdout.contains(flag), "{}", flag);
+    }
+}
+
+#[test]
+fn version_prints_the_package_version() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust")).arg("--version").output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("rust {}\n", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn unknown_options_print_the_usage() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust")).arg("--tokens").output().unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Unknown option --tokens\n\nUsage:"));
+}
+
+#[test]
+fn strict_overrides_permissive_console() {
+    let output = run(&["--permissive-console"], "a := 1 + CONSOLE 2\n");
+    assert!(output.status.success());
+
+    let output = run(&["--permissive-console", "--strict"], "a := 1 + CONSOLE 2\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Syntax error"));
+}
+
+#[test]
+fn coverage_marks_lines_that_ran() {
+    let output = run(&["--coverage"], "a := 0;\n\nwhile a > 0 {\n    a := a - 1\n}\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with(": 2/4 lines\n✓ a := 0;\n  \n✓ while a > 0 {\n·     a := a - 1\n· }\n"), "{}", stdout);
+}
+
+#[test]
+fn check_reports_syntax_errors_without_running() {
+    let output = run(&["--check"], "CONSOLE 1;\nx := 5 ++ 3\n");
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("\nSyntax error: operator '+' on line 2 is followed by another operator '+' on line 2 in file"), "{}", stdout);
+
+    let output = run(&["--check"], "CONSOLE 1\n");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+fn run_scenarios(scenarios: &str, program: &str) -> Output {
+    let paths = ["scenarios", "program"].map(temp_path);
+    std::fs::File::create(&paths[0]).unwrap().write_all(scenarios.as_bytes()).unwrap();
+    std::fs::File::create(&paths[1]).unwrap().write_all(program.as_bytes()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rust")).arg("--scenarios").args(&paths).output().unwrap();
+    for path in paths {
+        std::fs::remove_file(path).unwrap();
+    }
+    output
+}
+
+#[test]
+fn scenarios_run_the_program_once_each() {
+    let output = run_scenarios("n=2\nn=#A, x=0\n\nn=-1\n", "CONSOLE n * x + y;\nx := 100\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Scenario 1 (n=2):\n5\nScenario 2 (n=10, x=0):\n3\nScenario 4 (n=-1):\n2\n");
+}
+
+#[test]
+fn scenario_errors_name_the_line() {
+    let output = run_scenarios("n=1\nn 2\n", "CONSOLE n\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("\nScenario error: expected name=value, found 'n 2' on line 2 in file"));
+
+    let output = run_scenarios("to=1\n", "CONSOLE 1\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("'to' is not a variable name on line 1"));
+
+    let output = run_scenarios("n=1\nn=#G\n", "CONSOLE n\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("bad value for 'n' on line 2 (Literal error: invalid digit in literal)"));
+}
+
+#[test]
+fn reject_unused_values_rejects_bare_expressions() {
+    let output = run(&["--reject-unused-values"], "x := 1;\nx + 1\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Evaluation error: value of the statement starting with 'x' on line 2:1-1 is unused"));
+
+    let output = run(&["--reject-unused-values"], "x := 1;\nCONSOLE x + 1\n");
+    assert!(output.status.success());
+}
+
+#[test]
+fn collect_keeps_the_other_options() {
+    let output = run(&["--collect", "--max-steps", "100"], "i := 0;\nwhile 1 {\n    i := i + 1\n}\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Evaluation error: step limit exceeded"));
+
+    let output = run(&["--collect", "--max-depth", "2"], "a := (((1)))\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Syntax error: expression nested too deeply"));
+}
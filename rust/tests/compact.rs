@@ -0,0 +1,53 @@
+use rust::tokenizer::{tokenize, tokenize_compact, CompactTokens, Options};
+use rust::{eval, parser};
+use std::collections::HashMap;
+use std::fs;
+
+#[test]
+fn compact_tokens_convert_back_unchanged() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples");
+    for entry in fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "txt") {
+            let source = fs::read(&path).unwrap();
+            let tokens = tokenize(&source[..]).unwrap();
+            let compact = tokenize_compact(&source[..], Options::default()).unwrap();
+
+            assert_eq!(compact.to_token_infos(), tokens, "{}", path.display());
+            assert_eq!(CompactTokens::try_from(&tokens[..]).unwrap().to_token_infos(), tokens);
+        }
+    }
+}
+
+#[test]
+fn lexemes_point_into_the_shared_text() {
+    let compact = tokenize_compact("total := 12 + x\n".as_bytes(), Options::default()).unwrap();
+    let lexemes: Vec<&str> = compact.tokens().iter().map(|token| token.lexeme(compact.text())).collect();
+
+    assert_eq!(lexemes, ["total", ":=", "12", "+", "x", ""]);
+    assert_eq!(compact.text(), "total:=12+x");
+}
+
+#[test]
+fn compact_tokens_parse_and_evaluate_the_same() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples");
+    for entry in fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "txt") {
+            continue;
+        }
+
+        let source = fs::read(&path).unwrap();
+        let tokens = tokenize(&source[..]).unwrap();
+        let compact = tokenize_compact(&source[..], Options::default()).unwrap();
+        let parsed = parser::parse_with_options(&tokens, parser::Options::default()).map_err(|error| error.to_string());
+        assert_eq!(parser::parse_compact(&compact, parser::Options::default()).map_err(|error| error.to_string()), parsed, "{}", path.display());
+
+        let (mut variables, mut compact_variables) = (HashMap::new(), HashMap::new());
+        let options = eval::Options { max_steps: Some(100_000), ..Default::default() };
+        let value = eval::run(&tokens, &mut variables, options).map(|output| output.value).map_err(|error| error.to_string());
+        let compact_value = eval::run_compact(&compact, &mut compact_variables, options).map(|output| output.value).map_err(|error| error.to_string());
+        assert_eq!(compact_value, value, "{}", path.display());
+        assert_eq!(compact_variables, variables, "{}", path.display());
+    }
+}
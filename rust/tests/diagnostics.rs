@@ -0,0 +1,70 @@
+use rust::diagnostics::{Diagnostics, Severity};
+use rust::tokenizer::Position;
+use rust::{run_str_collect, RunOptions};
+use std::collections::HashMap;
+
+#[test]
+fn reports_tokenizer_and_parser_errors_together() {
+    let (result, diagnostics) = run_str_collect("b := (3 + 4;\na := 1 $ 2;\n", &mut HashMap::new(), RunOptions::default());
+
+    assert!(result.is_none());
+    assert_eq!(diagnostics.len(), 2);
+    let positions: Vec<Position> = diagnostics.iter().map(|diagnostic| diagnostic.position).collect();
+    assert_eq!(positions, [Position { row: 1, col: 12 }, Position { row: 2, col: 8 }]);
+    assert!(diagnostics.iter().nth(1).unwrap().message.starts_with("Tokenizer error"));
+}
+
+#[test]
+fn evaluates_only_clean_programs() {
+    let (result, diagnostics) = run_str_collect("a := 3 * 4\n", &mut HashMap::new(), RunOptions::default());
+    let output = result.unwrap();
+    assert_eq!(output.value, 12);
+    assert_eq!(output.unused_variables(), [(String::from("a"), Position { row: 1, col: 1 })]);
+    assert!(diagnostics.is_empty());
+
+    let (result, diagnostics) = run_str_collect("a := 4 / 0\n", &mut HashMap::new(), RunOptions::default());
+    assert!(result.is_none());
+    assert!(diagnostics.has_errors());
+    assert!(diagnostics.iter().next().unwrap().message.starts_with("Evaluation error"));
+}
+
+#[test]
+fn sorts_and_drops_repeats_at_the_same_position() {
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.error(Position { row: 3, col: 1 }, String::from("third"));
+    diagnostics.error(Position { row: 1, col: 5 }, String::from("first"));
+    diagnostics.error(Position { row: 1, col: 5 }, String::from("repeated"));
+    diagnostics.warning(Position { row: 1, col: 5 }, String::from("warning"));
+    diagnostics.sort_by_position();
+
+    let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+    assert_eq!(messages, ["first", "warning", "third"]);
+    assert_eq!(diagnostics.iter().nth(1).unwrap().severity, Severity::Warning);
+}
+
+#[test]
+fn renders_a_caret_under_the_column() {
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.error(Position { row: 2, col: 3 }, String::from("bad"));
+
+    assert_eq!(diagnostics.render("a := 1;\nb $ 2\n"), "error at 2:3: bad\n2 | b $ 2\n  |   ^\n");
+}
+
+#[test]
+fn duplicate_assignments_are_warnings() {
+    let (result, diagnostics) = run_str_collect("a := 1;\na := 2;\nCONSOLE a\n", &mut HashMap::new(), RunOptions::default());
+
+    assert!(result.is_some());
+    assert!(!diagnostics.has_errors());
+    let warning = diagnostics.iter().next().unwrap();
+    assert_eq!((warning.severity, warning.position), (Severity::Warning, Position { row: 2, col: 1 }));
+}
+
+#[test]
+fn constant_while_conditions_are_warnings() {
+    let (result, diagnostics) = run_str_collect("a := 1;\nwhile 2 < 1 { a := 2 };\nCONSOLE a\n", &mut HashMap::new(), RunOptions::default());
+
+    assert!(result.is_some());
+    let warning = diagnostics.iter().next().unwrap();
+    assert_eq!((warning.severity, warning.position), (Severity::Warning, Position { row: 2, col: 7 }));
+}
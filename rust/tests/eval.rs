@@ -0,0 +1,422 @@
+use rust::eval::{dump_vars, parse_with_options, run, DuplicateAssignment, Error, EvalLimits, ForBoundMode, Interpreter, Options, StatementOutcome};
+use rust::tokenizer::Position;
+use rust::tokenizer::tokenize;
+use std::collections::HashMap;
+
+fn run_with(program: &str, options: Options) -> HashMap<String, i64> {
+    let mut variables = HashMap::new();
+    parse_with_options(&tokenize(program.as_bytes()).unwrap(), &mut variables, options).unwrap();
+    variables
+}
+
+#[test]
+fn for_bound_modes_diverge_when_the_body_moves_the_bound() {
+    let program = "n := 3; count := 0;\nfor (i := 1 to n) begin\n    count := count + 1;\n    n := 5\nend\n";
+
+    let frozen = run_with(program, Options::default());
+    assert_eq!(frozen["count"], 3);
+
+    let reevaluated = run_with(program, Options { for_bound: ForBoundMode::Reevaluated, ..Default::default() });
+    assert_eq!(reevaluated["count"], 5);
+}
+
+#[test]
+fn reevaluated_bound_can_end_the_loop_early() {
+    let program = "n := 10; count := 0;\nfor (i := 1 to n) begin\n    count := count + 1;\n    n := 2\nend\n";
+
+    let reevaluated = run_with(program, Options { for_bound: ForBoundMode::Reevaluated, ..Default::default() });
+    assert_eq!(reevaluated["count"], 2);
+}
+
+fn unused(program: &str, variables: &mut HashMap<String, i64>) -> Vec<(String, Position)> {
+    run(&tokenize(program.as_bytes()).unwrap(), variables, Options::default()).unwrap().unused_variables()
+}
+
+#[test]
+fn reports_variables_that_are_never_read() {
+    let unused = unused("a := 1;\nb := 2;\nc := b;\na := 3\n", &mut HashMap::new());
+
+    assert_eq!(unused, [(String::from("c"), Position { row: 3, col: 1 }), (String::from("a"), Position { row: 4, col: 1 })]);
+}
+
+#[test]
+fn accumulators_read_in_their_own_update_are_used() {
+    assert!(unused("s := 0;\nfor (i := 1 to 3) begin s := s + 1 end\n", &mut HashMap::new()).is_empty());
+}
+
+#[test]
+fn loop_control_variables_are_excluded() {
+    assert!(unused("for (i := 1 to 3) begin CONSOLE 1 end\n", &mut HashMap::new()).is_empty());
+}
+
+#[test]
+fn seeded_variables_are_excluded() {
+    let mut variables = HashMap::from([(String::from("x"), 1)]);
+    assert!(unused("x := 5\n", &mut variables).is_empty());
+}
+
+#[test]
+fn vars_counts_as_reading_every_variable() {
+    assert!(unused("a := 1;\nVARS\n", &mut HashMap::new()).is_empty());
+}
+
+fn duplicates(program: &str) -> Vec<DuplicateAssignment> {
+    run(&tokenize(program.as_bytes()).unwrap(), &mut HashMap::new(), Options::default()).unwrap().duplicate_assignments()
+}
+
+#[test]
+fn reports_both_assignments() {
+    let found = duplicates("x := 5 * 3;\nx := 0;\nCONSOLE x\n");
+
+    assert_eq!(found, [DuplicateAssignment { name: String::from("x"), first: Position { row: 1, col: 1 }, second: Position { row: 2, col: 1 } }]);
+    assert_eq!(found[0].to_string(), "Warning: 'x' assigned on line 1 is assigned again on line 2 without being read");
+}
+
+#[test]
+fn a_read_in_between_or_on_the_right_hand_side_keeps_the_value() {
+    assert!(duplicates("x := 1;\nCONSOLE x;\nx := 2\n").is_empty());
+    assert!(duplicates("x := 1;\nx := x + 1\n").is_empty());
+    assert!(duplicates("x := 1;\nVARS;\nx := 2\n").is_empty());
+}
+
+#[test]
+fn accumulating_in_a_loop_is_not_a_duplicate() {
+    assert!(duplicates("s := 0;\nfor (i := 1 to 5) begin\n    s := s + i\nend;\nCONSOLE s\n").is_empty());
+    assert!(duplicates("last := 0;\nfor (i := 1 to 5) begin\n    last := i\nend;\nCONSOLE last\n").is_empty());
+    assert!(duplicates("i := 0;\nwhile i < 5 {\n    i := i + 1\n}\n").is_empty());
+}
+
+#[test]
+fn duplicates_inside_a_loop_body_are_found_once() {
+    let found = duplicates("for (i := 1 to 5) begin\n    a := i;\n    a := 2 * i\nend;\nCONSOLE a\n");
+
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].first.row, found[0].second.row), (2, 3));
+}
+
+#[test]
+fn writes_in_a_loop_body_keep_an_earlier_duplicate() {
+    let found = duplicates("a := 0;\nfor (i := 1 to 2) begin\n    a := i\nend;\na := 5;\nCONSOLE a\n");
+
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].first.row, found[0].second.row), (1, 5));
+}
+
+#[test]
+fn only_code_that_runs_is_checked() {
+    assert!(duplicates("n := 0;\nwhile n {\n    a := 1;\n    a := 2\n};\nCONSOLE n\n").is_empty());
+}
+
+#[test]
+fn dump_vars_is_sorted_and_stable() {
+    let dump = || {
+        let variables: HashMap<String, i64> = (0..50).map(|i| (format!("v{}", 49 - i), i)).collect();
+        let mut output = Vec::new();
+        dump_vars(&variables, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    let first = dump();
+    assert!(first.starts_with("v0 = 49\nv1 = 48\nv10 = 39\n"));
+    for _ in 0..10 {
+        assert_eq!(dump(), first);
+    }
+}
+
+fn evaluate(program: &str) -> Result<i64, rust::eval::Error> {
+    rust::eval::parse(&tokenize(program.as_bytes()).unwrap(), &mut HashMap::new())
+}
+
+#[test]
+fn clamp_keeps_values_within_bounds() {
+    assert_eq!(evaluate("clamp(-5, 0, 10)").unwrap(), 0);
+    assert_eq!(evaluate("clamp(0, 0, 10)").unwrap(), 0);
+    assert_eq!(evaluate("clamp(7, 0, 10)").unwrap(), 7);
+    assert_eq!(evaluate("clamp(10, 0, 10)").unwrap(), 10);
+    assert_eq!(evaluate("clamp(11, 0, 10)").unwrap(), 10);
+    assert_eq!(evaluate("clamp(3, 3, 3)").unwrap(), 3);
+}
+
+#[test]
+fn clamp_rejects_bad_arguments() {
+    let error = evaluate("clamp(5, 10, 0)").unwrap_err();
+    assert_eq!(error.to_string(), "Evaluation error: invalid argument to 'clamp' on line 1:1-5: lower bound 10 is greater than upper bound 0");
+
+    assert!(matches!(evaluate("clamp(5, 10)").unwrap_err(), rust::eval::Error::ArgumentCount(_, 3)));
+    assert!(matches!(evaluate("abs(1, 2)").unwrap_err(), rust::eval::Error::ArgumentCount(_, 1)));
+}
+
+#[test]
+fn integer_literals_accept_leading_zeros_and_reject_huge_values() {
+    assert_eq!(evaluate("007").unwrap(), 7);
+    assert_eq!(evaluate("0").unwrap(), 0);
+
+    let error = evaluate("a := 1234567890123456789012345").unwrap_err();
+    assert!(matches!(error, Error::IntegerOverflow(_)));
+    assert_eq!(error.to_string(), "Evaluation error: integer literal '1234567890123456789012345' on line 1:6-30 is out of range");
+}
+
+#[test]
+fn every_literal_form_parses_and_reports_malformed_input() {
+    assert_eq!(evaluate("42").unwrap(), 42);
+    assert!(matches!(evaluate("9223372036854775808").unwrap_err(), Error::IntegerOverflow(_)));
+
+    assert_eq!(evaluate("#ff + #FF").unwrap(), 510);
+    assert_eq!(evaluate("#7FFFFFFFFFFFFFFF").unwrap(), i64::MAX);
+    assert!(matches!(evaluate("#10000000000000000").unwrap_err(), Error::IntegerOverflow(_)));
+    // a `#` without hex digits, at the end of the input or before a letter that isn't one
+    for program in ["#", "a := #", "#G"] {
+        let error = evaluate(program).unwrap_err();
+        assert!(matches!(&error, Error::InvalidNumber(token_info) if token_info.lexeme == "#"), "{}: {}", program, error);
+    }
+
+    // there are no `0x` or `_` forms: the tokenizer splits `0x10` into `0` and `x10`, and
+    // rejects `_` outright, so neither reaches parse_number
+    assert!(matches!(evaluate("0x10").unwrap_err(), Error::MissingSemicolon(_)));
+    assert!(tokenize("1_000".as_bytes()).is_err());
+}
+
+#[test]
+fn negation_and_abs_report_overflow_for_i64_min() {
+    assert_eq!(evaluate("-(-5)").unwrap(), 5);
+    assert_eq!(evaluate("abs(-7)").unwrap(), 7);
+
+    let min = "(-9223372036854775807 - 1)";
+    assert_eq!(evaluate(min).unwrap(), i64::MIN);
+    assert!(matches!(evaluate(&format!("-{}", min)).unwrap_err(), Error::ArithmeticOverflow(_)));
+    assert!(matches!(evaluate(&format!("abs{}", min)).unwrap_err(), Error::ArithmeticOverflow(_)));
+}
+
+fn run_limited(program: &str, limits: EvalLimits) -> Result<i64, Error> {
+    let options = Options { limits, ..Default::default() };
+    parse_with_options(&tokenize(program.as_bytes()).unwrap(), &mut HashMap::new(), options)
+}
+
+#[test]
+fn statement_limit_counts_loop_bodies() {
+    let program = "s := 0;\nfor (i := 1 to 10) begin s := s + i end\n";
+    let limits = |max_statements| EvalLimits { max_statements: Some(max_statements), ..Default::default() };
+
+    assert_eq!(run_limited(program, limits(12)).unwrap(), 0);
+    let error = run_limited(program, limits(11)).unwrap_err();
+    assert!(matches!(error, Error::StatementLimitExceeded(_)));
+    assert_eq!(error.token_info().start_position.row, 2);
+}
+
+#[test]
+fn expression_depth_limit_counts_nesting() {
+    let limits = EvalLimits { max_expr_depth: Some(4), ..Default::default() };
+
+    assert_eq!(run_limited("a := ((1))", limits).unwrap(), 1);
+    let error = run_limited("a := (((1)))", limits).unwrap_err();
+    assert!(matches!(error, Error::ExpressionTooDeep(_)));
+    assert_eq!(error.token_info().start_position.col, 9);
+}
+
+#[test]
+fn variable_limit_only_applies_to_new_names() {
+    let limits = EvalLimits { max_variables: Some(2), ..Default::default() };
+
+    assert!(run_limited("a := 1; b := 2; a := 3; b := 4", limits).is_ok());
+    let error = run_limited("a := 1; b := 2; c := 3", limits).unwrap_err();
+    assert!(matches!(error, Error::TooManyVariables(_)));
+    assert_eq!(error.token_info().lexeme, "c");
+}
+
+#[test]
+fn runtime_errors_print_the_span_of_the_token() {
+    let error = evaluate("a := 1;\nb := a + total\n").unwrap_err();
+
+    assert!(matches!(error, Error::UndefinedVariable(_)));
+    assert_eq!(error.token_info().end_position(), rust::tokenizer::Position { row: 2, col: 14 });
+    assert_eq!(error.to_string(), "Evaluation error: variable 'total' on line 2:10-14 undefined");
+}
+
+#[test]
+fn run_iter_yields_one_outcome_per_statement() {
+    let tokens = tokenize("a := 2;\nfor (i := 1 to 3) begin CONSOLE a * i end;\nCONSOLE a + 10\n".as_bytes()).unwrap();
+    let mut interpreter = Interpreter::default();
+    let outcomes: Vec<StatementOutcome> = interpreter.run_iter(&tokens).map(Result::unwrap).collect();
+
+    assert_eq!(outcomes, [
+        StatementOutcome { line: 1, value: 2, console_output: String::new() },
+        StatementOutcome { line: 2, value: 0, console_output: String::from("2\n4\n6\n") },
+        StatementOutcome { line: 3, value: 0, console_output: String::from("12\n") }
+    ]);
+    assert_eq!(interpreter.variables["a"], 2);
+}
+
+#[test]
+fn vars_prints_every_variable_in_name_order() {
+    let tokens = tokenize("b := 2;\na := 1;\nc10 := 4;\nc9 := 3;\nVARS\n".as_bytes()).unwrap();
+    let mut interpreter = Interpreter::default();
+    let outcome = interpreter.run_iter(&tokens).map(Result::unwrap).last().unwrap();
+
+    assert_eq!(outcome.console_output, "a = 1\nb = 2\nc10 = 4\nc9 = 3\n");
+}
+
+#[test]
+fn run_iter_stops_after_the_first_error() {
+    let tokens = tokenize("a := 1;\nb := a / 0;\nc := 3\n".as_bytes()).unwrap();
+    let mut interpreter = Interpreter::default();
+    let mut outcomes = interpreter.run_iter(&tokens);
+
+    assert_eq!(outcomes.next().unwrap().unwrap().line, 1);
+    assert!(matches!(outcomes.next(), Some(Err(Error::DivisionByZero(_)))));
+    assert!(outcomes.next().is_none());
+    assert!(!interpreter.variables.contains_key("c"));
+}
+
+#[test]
+fn forks_evaluate_one_parsed_program_independently() {
+    let program = rust::parser::parse_program(tokenize("y := x * x + 1\n".as_bytes()).unwrap(), &rust::parser::Options::default()).unwrap();
+    let mut base = Interpreter::default();
+    base.variables.insert(String::from("x"), 0);
+
+    for x in 1..=3 {
+        let mut fork = base.fork();
+        fork.variables.insert(String::from("x"), x);
+        fork.eval_parsed(&program).unwrap();
+        assert_eq!(fork.variables["y"], x * x + 1);
+    }
+    assert!(!base.variables.contains_key("y"));
+}
+
+#[test]
+fn parse_program_rejects_invalid_syntax() {
+    assert!(rust::parser::parse_program(tokenize("y := (1 +\n".as_bytes()).unwrap(), &rust::parser::Options::default()).is_err());
+}
+
+#[test]
+fn parse_program_uses_the_given_options() {
+    let tokens = tokenize("y := ((1))\n".as_bytes()).unwrap();
+    let shallow = rust::parser::Options { max_depth: 1, ..Default::default() };
+
+    assert!(rust::parser::parse_program(tokens.clone(), &rust::parser::Options::default()).is_ok());
+    assert!(matches!(rust::parser::parse_program(tokens, &shallow), Err(rust::parser::Error::NestingTooDeep(_))));
+}
+
+/// Run with `cargo test --release -- --ignored --nocapture`. Parsing once and evaluating a
+/// thousand variable sets spends nearly all of its time evaluating.
+#[test]
+#[ignore]
+fn benchmark_one_parse_and_many_evaluations() {
+    let source = "total := 0;\nfor (i := 1 to n) begin\n    total := total + i * x\nend;\ny := total / 7\n";
+    let tokens = tokenize(source.as_bytes()).unwrap();
+
+    let started = std::time::Instant::now();
+    let program = rust::parser::parse_program(tokens, &rust::parser::Options::default()).unwrap();
+    let parsing = started.elapsed();
+
+    let started = std::time::Instant::now();
+    for x in 0..1000 {
+        let mut interpreter = Interpreter::default();
+        interpreter.variables.insert(String::from("n"), 100);
+        interpreter.variables.insert(String::from("x"), x);
+        interpreter.eval_parsed(&program).unwrap();
+    }
+    let evaluating = started.elapsed();
+
+    println!("parse: {:?}, 1000 evaluations: {:?}", parsing, evaluating);
+    assert!(evaluating > parsing);
+}
+
+/// Run with `cargo test --release -- --ignored --nocapture`. Every iteration matches a few
+/// dozen tokens, so this shows what each `match_token` costs.
+#[test]
+#[ignore]
+fn benchmark_a_million_loop_iterations() {
+    let source = "a := 0;\nfor (i := 1 to 1000000) begin\n    a := a + i;\n    b := a / 2\nend\n";
+    let tokens = tokenize(source.as_bytes()).unwrap();
+
+    let mut variables = HashMap::new();
+
+    let started = std::time::Instant::now();
+    parse_with_options(&tokens, &mut variables, Options::default()).unwrap();
+    println!("1000000 iterations: {:?}", started.elapsed());
+    assert_eq!(variables["a"], 500_000_500_000);
+}
+
+fn coverage(program: &str) -> (Vec<u32>, Vec<u32>) {
+    let options = Options { coverage: true, ..Default::default() };
+    let output = run(&tokenize(program.as_bytes()).unwrap(), &mut HashMap::new(), options).unwrap();
+    (output.covered_lines().into_iter().collect(), output.uncovered_lines().into_iter().collect())
+}
+
+#[test]
+fn loops_that_never_run_leave_their_body_uncovered() {
+    let program = "n := 0;\n\nfor (i := 1 to n) begin\n    a := i;\n    b := a\nend;\n\nwhile n > 0 {\n    n := n - 1\n}\n";
+    // the semicolon after `end` is evaluated, so line 6 counts as covered
+    assert_eq!(coverage(program), (vec![1, 3, 6, 8], vec![4, 5, 9, 10]));
+}
+
+#[test]
+fn every_line_of_a_loop_that_ran_is_covered() {
+    let program = "s := 0;\nfor (i := 1 to 2) begin\n    s := s + i\nend\n";
+    assert_eq!(coverage(program), (vec![1, 2, 3, 4], vec![]));
+}
+
+#[test]
+fn coverage_is_only_recorded_when_asked_for() {
+    let output = run(&tokenize("s := 0;\nwhile s > 0 {\n    s := s - 1\n}\n".as_bytes()).unwrap(), &mut HashMap::new(), Options::default()).unwrap();
+    assert!(output.covered_lines().is_empty() && output.uncovered_lines().is_empty());
+}
+
+#[test]
+fn for_without_to_is_rejected() {
+    let error = evaluate("for (i := 1 3) begin\n    i\nend\n").unwrap_err();
+    assert!(matches!(error, Error::InvalidFor(_)), "{}", error);
+    assert_eq!(error.token_info().lexeme, "3");
+}
+
+// like the parser, a missing parenthesis is reported as such rather than as InvalidFor
+#[test]
+fn for_without_parantheses_is_rejected() {
+    let error = evaluate("for i := 1 to 3 begin\n    i\nend\n").unwrap_err();
+    assert!(matches!(error, Error::ExpectedStartingParantheses(_)), "{}", error);
+    assert_eq!(error.token_info().lexeme, "i");
+
+    let error = evaluate("for (i := 1 to 3 begin\n    i\nend\n").unwrap_err();
+    assert!(matches!(error, Error::MissingClosingParantheses(_)), "{}", error);
+    assert_eq!(error.token_info().lexeme, "begin");
+}
+
+#[test]
+fn for_with_a_non_identifier_variable_is_rejected() {
+    for (program, lexeme) in [("for (5 := 1 to 3) begin\n    5\nend\n", "5"), ("for (to := 1 to 3) begin\n    1\nend\n", "to")] {
+        let error = evaluate(program).unwrap_err();
+        assert!(matches!(error, Error::InvalidAssignment(_, _)), "{}", error);
+        assert_eq!(error.token_info().lexeme, lexeme);
+    }
+}
+
+#[test]
+fn undefined_for_bound_points_at_the_variable() {
+    let error = evaluate("a := 1;\nfor(i := 0 to z) begin i end\n").unwrap_err();
+    assert!(matches!(error, Error::UndefinedVariable(_)), "{}", error);
+    assert_eq!(error.token_info().start_position, Position { row: 2, col: 15 });
+}
+
+fn console_output(program: &str, group_digits: bool) -> String {
+    let tokens = tokenize(program.as_bytes()).unwrap();
+    let mut interpreter = Interpreter::new(Options { group_digits, ..Default::default() });
+    interpreter.run_iter(&tokens).map(|outcome| outcome.unwrap().console_output).collect()
+}
+
+#[test]
+fn console_groups_digits_only_when_asked() {
+    let program = "CONSOLE 1000000;\nCONSOLE -1000;\nCONSOLE 999\n";
+    assert_eq!(console_output(program, false), "1000000\n-1000\n999\n");
+    assert_eq!(console_output(program, true), "1_000_000\n-1_000\n999\n");
+}
+
+#[test]
+fn unused_values_can_be_rejected() {
+    let options = Options { reject_unused_values: true, ..Default::default() };
+    let error = parse_with_options(&tokenize("x := 1;\n1 + 1;\n".as_bytes()).unwrap(), &mut HashMap::new(), options).unwrap_err();
+    assert!(matches!(error, Error::UnusedValue(_)), "{}", error);
+    assert_eq!(error.token_info().start_position.row, 2);
+
+    assert_eq!(run_with("CONSOLE 1 + 1;\nx := 1 + 1;\n", options)["x"], 2);
+    assert_eq!(evaluate("1 + 1;\n").unwrap(), 2);
+}
@@ -0,0 +1,68 @@
+use rust::fuzz::{fuzz_one, Outcome};
+use rust::{eval, tokenizer};
+use std::collections::HashMap;
+use std::fs;
+
+const FRAGMENTS: [&str; 34] = [
+    "a", "b", "x", " ", "\n", ":=", "0", "1", "12", "#F", "#", ";", "(", ")", "{", "}", "for", "while",
+    "begin", "end", "to", "CONSOLE", "VARS", "+", "-", "*", "/", "&", "|", "<", "==", "..",
+    "9223372036854775807", ","
+];
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+#[test]
+fn corpus_does_not_panic() {
+    for entry in fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus")).unwrap() {
+        let input = fs::read(entry.unwrap().path()).unwrap();
+        fuzz_one(&input);
+    }
+}
+
+#[test]
+fn random_fragments_do_not_panic() {
+    let mut rng = XorShift(0x5eed_0001);
+    for _ in 0..20_000 {
+        let mut input = String::new();
+        for _ in 0..rng.below(16) {
+            input.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())]);
+            input.push(' ');
+        }
+        fuzz_one(input.as_bytes());
+    }
+}
+
+#[test]
+fn random_bytes_do_not_panic() {
+    let mut rng = XorShift(0x5eed_0002);
+    for _ in 0..20_000 {
+        let input: Vec<u8> = (0..rng.below(24)).map(|_| rng.below(256) as u8).collect();
+        fuzz_one(&input);
+    }
+}
+
+#[test]
+fn looping_programs_run_out_of_fuel() {
+    let input = b"for (i := 0 to 1000000000) begin\ni\nend\n";
+    assert_eq!(fuzz_one(input), Outcome::EvaluationError);
+}
+
+#[test]
+fn nesting_past_the_depth_limit_is_an_error() {
+    let input = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus/nested_loops.txt")).unwrap();
+    assert_eq!(fuzz_one(&input), Outcome::SyntaxError);
+
+    // evaluating without parsing first stops at the same depth
+    let tokens = tokenizer::tokenize(input.as_slice()).unwrap();
+    let error = eval::parse(&tokens, &mut HashMap::new()).unwrap_err();
+    assert!(matches!(error, eval::Error::ExpressionTooDeep(_)), "{}", error);
+}
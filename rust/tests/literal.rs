@@ -0,0 +1,56 @@
+use rust::literal::{format_literal, group_digits, parse_literal, LiteralError, LiteralRadix};
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn formats_both_radixes() {
+    assert_eq!(format_literal(255, LiteralRadix::Decimal), "255");
+    assert_eq!(format_literal(255, LiteralRadix::Hex), "#FF");
+    assert_eq!(format_literal(-255, LiteralRadix::Hex), "-#FF");
+    assert_eq!(format_literal(0, LiteralRadix::Hex), "#0");
+    assert_eq!(format_literal(i64::MIN, LiteralRadix::Hex), "-#8000000000000000");
+}
+
+#[test]
+fn parses_what_the_tokenizer_accepts() {
+    assert_eq!(parse_literal("007"), Ok(7));
+    assert_eq!(parse_literal("#ff"), Ok(255));
+    assert_eq!(parse_literal("#0010"), Ok(16));
+    assert_eq!(parse_literal("-9223372036854775808"), Ok(i64::MIN));
+    assert_eq!(parse_literal("9223372036854775808"), Err(LiteralError::Overflow));
+    assert_eq!(parse_literal("#"), Err(LiteralError::Empty));
+    assert_eq!(parse_literal("+5"), Err(LiteralError::InvalidDigit));
+    assert_eq!(parse_literal("12a"), Err(LiteralError::InvalidDigit));
+}
+
+#[test]
+fn round_trips_random_values() {
+    let mut rng = XorShift(0x5eed_0003);
+    let edges = [0, 1, -1, i64::MAX, i64::MIN];
+    let values = edges.into_iter().chain((0..10_000).map(|_| rng.next() as i64));
+    for value in values {
+        for radix in [LiteralRadix::Decimal, LiteralRadix::Hex] {
+            assert_eq!(parse_literal(&format_literal(value, radix)), Ok(value), "{:?} {}", radix, value);
+        }
+    }
+}
+
+#[test]
+fn groups_digits_in_threes() {
+    assert_eq!(group_digits(1_000_000), "1_000_000");
+    assert_eq!(group_digits(-1000), "-1_000");
+    assert_eq!(group_digits(999), "999");
+    assert_eq!(group_digits(-12), "-12");
+    assert_eq!(group_digits(0), "0");
+    assert_eq!(group_digits(i64::MAX), "9_223_372_036_854_775_807");
+    assert_eq!(group_digits(i64::MIN), "-9_223_372_036_854_775_808");
+}
@@ -0,0 +1,54 @@
+//! Measures what the token streams really allocate. A counting allocator serves the whole
+//! test binary, so this file has a single test and nothing runs alongside it.
+
+use rust::tokenizer::{tokenize, tokenize_compact, Options};
+use rust::{eval, parser};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Bytes still allocated by whatever `build` returns, as long as it's kept alive.
+fn retained<T>(build: impl FnOnce() -> T) -> (T, usize) {
+    let before = LIVE.load(Ordering::Relaxed);
+    let value = build();
+    (value, LIVE.load(Ordering::Relaxed) - before)
+}
+
+#[test]
+fn a_million_compact_tokens_take_a_fraction_of_the_memory() {
+    // 4 tokens per line
+    let source = "abc := 12;\n".repeat(250_000);
+    let (tokens, full) = retained(|| tokenize(source.as_bytes()).unwrap());
+    let (compact, small) = retained(|| tokenize_compact(source.as_bytes(), Options::default()).unwrap());
+    assert_eq!((tokens.len(), compact.tokens().len()), (1_000_001, 1_000_001));
+
+    // 75 MB against 19 MB, the Vec's spare capacity included
+    println!("TokenInfo: {} bytes, CompactTokens: {} bytes", full, small);
+    assert!(small * 3 <= full, "{} vs {}", small, full);
+
+    // parsing and evaluating work on the compact form directly
+    drop(tokens);
+    parser::parse_compact(&compact, parser::Options::default()).unwrap();
+    let mut variables = HashMap::new();
+    assert_eq!(eval::run_compact(&compact, &mut variables, eval::Options::default()).unwrap().value, 12 * 250_000);
+    assert_eq!(variables["abc"], 12);
+}
@@ -0,0 +1,45 @@
+use rust::tokenizer::tokenize;
+use rust::{eval, parser};
+use std::collections::HashMap;
+
+fn parser_message(program: &str) -> String {
+    parser::parse(&tokenize(program.as_bytes()).unwrap(), parser::DEFAULT_MAX_DEPTH).unwrap_err().to_string()
+}
+
+fn eval_message(program: &str) -> String {
+    eval::parse(&tokenize(program.as_bytes()).unwrap(), &mut HashMap::new()).unwrap_err().to_string()
+}
+
+#[test]
+fn context_shows_what_came_before() {
+    assert_eq!(parser_message("a := 1;\nx := 5 + ;\n"),
+        "Syntax error: unexpected token ';' of type SEMICOLON after \"x := 5 +\" on line 2");
+    assert_eq!(eval_message("x := 5 + ;\n"),
+        "Syntax error: unexpected token ';' of type SEMICOLON after \"x := 5 +\" on line 1:10-10");
+}
+
+#[test]
+fn context_at_the_start_of_input_is_never_empty() {
+    assert_eq!(parser_message(") + 1\n"),
+        "Syntax error: unexpected token ')' of type RIGHT_PARANTHESES after start of input on line 1");
+    assert_eq!(eval_message(") + 1\n"),
+        "Syntax error: unexpected token ')' of type RIGHT_PARANTHESES after start of input on line 1:1-1");
+}
+
+#[test]
+fn long_context_is_trimmed_from_the_front() {
+    assert_eq!(parser_message("x := averagetemperatureacrossallsensors + correctionforaltitude - )\n"),
+        "Syntax error: unexpected token ')' of type RIGHT_PARANTHESES after \"...rossallsensors + correctionforaltitude -\" on line 1");
+}
+
+#[test]
+fn invalid_for_header_shows_the_loop_start() {
+    assert_eq!(parser_message("for (1 to 3) begin end\n"),
+        "Syntax error: invalid assignment; found '1' of type INT after \"for (\" on line 1");
+}
+
+#[test]
+fn context_does_not_reach_into_the_previous_statement() {
+    assert_eq!(parser_message("a := 1;\n) + 1\n"),
+        "Syntax error: unexpected token ')' of type RIGHT_PARANTHESES after start of statement on line 2");
+}
@@ -0,0 +1,248 @@
+use rust::parser::{classify, parse, parse_slim, parse_with_options, Error, Options, ParseOutcome, DEFAULT_MAX_DEPTH};
+use rust::eval;
+use rust::tokenizer::{self, tokenize, tokenize_slim, tokenize_with_options, Strictness, RESERVED_WORDS};
+use std::collections::HashMap;
+
+fn parse_error(program: &str) -> Error {
+    parse(&tokenize(program.as_bytes()).unwrap(), DEFAULT_MAX_DEPTH).unwrap_err()
+}
+
+#[test]
+fn for_without_to_points_at_the_offending_token() {
+    let error = parse_error("a := 1;\nfor (i := 1 end 5) begin\n    a\nend\n");
+
+    assert!(matches!(error, Error::InvalidFor(_)));
+    assert_eq!(error.token_info().lexeme, "end");
+    assert_eq!(error.to_string(), "Syntax error: invalid for loop structure, unexpected token 'end' of type END on line 2");
+}
+
+#[test]
+fn for_without_closing_parantheses_points_at_the_offending_token() {
+    let error = parse_error("a := 1;\n\nfor (i := 1 to 5 begin\n    a\nend\n");
+
+    assert!(matches!(error, Error::MissingClosingParantheses(_)));
+    assert_eq!(error.token_info().lexeme, "begin");
+    assert_eq!(error.token_info().start_position.row, 3);
+    assert_eq!(error.to_string(), "Syntax error: missing closing parantheses on line 3");
+}
+
+#[test]
+fn for_without_begin_points_at_the_offending_token() {
+    let error = parse_error("for (i := 1 to 5)\n    a\nend\n");
+
+    assert!(matches!(error, Error::InvalidFor(_)));
+    assert_eq!(error.token_info().lexeme, "a");
+    assert_eq!(error.to_string(), "Syntax error: invalid for loop structure, unexpected token 'a' of type IDENTIFIER on line 2");
+}
+
+#[test]
+fn reserved_words_cannot_be_assigned() {
+    for (word, _) in RESERVED_WORDS {
+        let program = format!("a := 1;\n{} := 5\n", word);
+        let tokens = tokenize(program.as_bytes()).unwrap();
+
+        let error = parse(&tokens, DEFAULT_MAX_DEPTH).unwrap_err();
+        assert!(matches!(error, Error::ReservedWord(_)), "{}", word);
+        assert!(error.to_string().starts_with(&format!("Syntax error: '{}' is a reserved word", word)));
+
+        let error = eval::parse(&tokens, &mut HashMap::new()).unwrap_err();
+        assert!(matches!(error, eval::Error::ReservedWord(_)), "{}", word);
+        assert_eq!(error.token_info().start_position.row, 2);
+    }
+}
+
+#[test]
+fn reserved_words_cannot_be_printed() {
+    for word in ["in", "begin", "end", "to"] {
+        let program = format!("CONSOLE {}\n", word);
+        let tokens = tokenize(program.as_bytes()).unwrap();
+
+        let error = parse(&tokens, DEFAULT_MAX_DEPTH).unwrap_err();
+        assert!(error.to_string().ends_with("reserved words are for, while, in, begin, end, to, CONSOLE, VARS"), "{}", error);
+
+        let error = eval::parse(&tokens, &mut HashMap::new()).unwrap_err();
+        assert!(matches!(error, eval::Error::ReservedWord(_)), "{}", word);
+    }
+}
+
+fn check_console(program: &str, permissive_console: bool) -> (Result<(), Error>, Result<i64, eval::Error>) {
+    let tokens = tokenize(program.as_bytes()).unwrap();
+    let parsed = parse_with_options(&tokens, Options { permissive_console, ..Default::default() });
+    let evaluated = eval::parse_with_options(&tokens, &mut HashMap::new(), eval::Options { permissive_console, ..Default::default() });
+    (parsed, evaluated)
+}
+
+#[test]
+fn console_argument_cannot_assign() {
+    let programs = ["a := 1;\nCONSOLE a := 5\n", "a := 1;\nCONSOLE 1 + (a := 5)\n", "a := 1;\nCONSOLE { a := 5 }\n", "a := 1;\nCONSOLE { b := 2; a := 5 }\n"];
+    for program in programs {
+        let (parsed, evaluated) = check_console(program, false);
+        let error = parsed.unwrap_err();
+        assert!(matches!(error, Error::InvalidAssignment(_, _)), "{}", program);
+        assert_eq!(error.token_info().start_position.row, 2);
+        assert!(matches!(evaluated.unwrap_err(), eval::Error::InvalidAssignment(_, _)), "{}", program);
+
+        let (parsed, evaluated) = check_console(program, true);
+        assert!(parsed.is_ok() && evaluated.is_ok(), "{}", program);
+    }
+}
+
+#[test]
+fn console_is_only_a_statement() {
+    let (parsed, evaluated) = check_console("a := 1 + CONSOLE 2\n", false);
+    assert_eq!(parsed.unwrap_err().token_info().lexeme, "CONSOLE");
+    assert!(evaluated.is_err());
+
+    let (parsed, evaluated) = check_console("a := 1 + CONSOLE 2\n", true);
+    assert!(parsed.is_ok() && evaluated.is_ok());
+}
+
+#[test]
+fn console_in_blocks_is_a_statement() {
+    let (parsed, evaluated) = check_console("for (i := 1 to 2) begin CONSOLE i; a := i end;\nwhile 0 { CONSOLE 1 }\n", false);
+    assert!(parsed.is_ok() && evaluated.is_ok());
+}
+
+#[test]
+fn doubled_operators_name_both() {
+    for (program, first, second) in [("x := 5 ++ 3\n", "+", "+"), ("CONSOLE 4 ** 2\n", "*", "*"), ("x := 1 <\n> 2\n", "<", ">"), ("x := 5 - * 3\n", "-", "*")] {
+        let tokens = tokenize(program.as_bytes()).unwrap();
+
+        match parse(&tokens, DEFAULT_MAX_DEPTH).unwrap_err() {
+            Error::DoubledOperator(token_info, next) => assert_eq!((token_info.lexeme.as_str(), next.lexeme.as_str()), (first, second)),
+            error => panic!("{}: {}", program, error)
+        }
+        assert!(matches!(eval::parse(&tokens, &mut HashMap::new()).unwrap_err(), eval::Error::DoubledOperator(_, _)), "{}", program);
+    }
+
+    let tokens = tokenize("x := 5 ++ 3\n".as_bytes()).unwrap();
+    assert_eq!(parse(&tokens, DEFAULT_MAX_DEPTH).unwrap_err().to_string(), "Syntax error: operator '+' on line 1 is followed by another operator '+' on line 1");
+}
+
+#[test]
+fn signs_after_an_operator_are_not_doubled() {
+    for (program, value) in [("x := 5 * -3\n", -15), ("x := 5 - -3\n", 8), ("x := 5 + +3\n", 8), ("x := 5 + -3\n", 2)] {
+        let tokens = tokenize(program.as_bytes()).unwrap();
+        assert!(parse(&tokens, DEFAULT_MAX_DEPTH).is_ok(), "{}", program);
+
+        let mut variables = HashMap::new();
+        eval::parse(&tokens, &mut variables).unwrap();
+        assert_eq!(variables["x"], value, "{}", program);
+    }
+}
+
+#[test]
+fn statements_cannot_start_with_a_binary_operator() {
+    for operator in ["*", "/", "&", "|", "<", ">", "=="] {
+        let program = format!("a := 1;\n{} 5\n", operator);
+        let tokens = tokenize(program.as_bytes()).unwrap();
+
+        let error = parse(&tokens, DEFAULT_MAX_DEPTH).unwrap_err();
+        assert!(matches!(error, Error::ExpressionExpected(_)), "{}", error);
+        assert_eq!(error.to_string(), format!("Syntax error: expected a value or '(' at the start of a statement, found '{}' on line 2", operator));
+        assert!(matches!(eval::parse(&tokens, &mut HashMap::new()).unwrap_err(), eval::Error::ExpressionExpected(_)), "{}", operator);
+    }
+
+    let error = parse_error("for (i := 1 to 2) begin\n    * i\nend\n");
+    assert_eq!(error.token_info().start_position.row, 2);
+}
+
+#[test]
+fn statements_can_start_with_a_sign() {
+    for program in ["+ 5\n", "- 5\n"] {
+        assert!(parse(&tokenize(program.as_bytes()).unwrap(), DEFAULT_MAX_DEPTH).is_ok(), "{}", program);
+    }
+}
+
+/// Checks `source` both ways, in the legacy and the strict mode, and compares the verdicts.
+fn same_verdict(source: &[u8]) {
+    for strictness in [Strictness::Legacy, Strictness::Strict] {
+        let tokenizer_options = tokenizer::Options { strictness, ..Default::default() };
+        let options = Options { strictness, ..Default::default() };
+        let full = tokenize_with_options(source, tokenizer_options).map(|tokens| parse_with_options(&tokens, options));
+        let slim = tokenize_slim(source, tokenizer_options).map(|tokens| parse_slim(&tokens, options));
+        match (full, slim) {
+            (Ok(full), Ok(slim)) => {
+                assert_eq!(full.is_ok(), slim.is_ok(), "{}", String::from_utf8_lossy(source));
+                if let (Err(full), Err(slim)) = (full, slim) {
+                    assert_eq!(full.token_info().start_position, slim.token_info().start_position);
+                }
+            },
+            (Err(_), Err(_)) => {},
+            _ => panic!("only one tokenizer failed on {}", String::from_utf8_lossy(source))
+        }
+    }
+}
+
+#[test]
+fn slim_tokens_are_accepted_and_rejected_the_same() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let paths: Vec<_> = [format!("{}/../examples", root), format!("{}/tests/corpus", root)].iter()
+        .flat_map(|dir| std::fs::read_dir(dir).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+
+    for path in paths {
+        same_verdict(&std::fs::read(path).unwrap());
+    }
+
+    for program in ["x := 5 ++ 3", "x := 5 + +3", "* 2", "CONSOLE := 1", "a := 1 + CONSOLE 2", "for (i := 1 to 2) { i end", "x := (1"] {
+        same_verdict(program.as_bytes());
+    }
+}
+
+/// Run with `cargo test --release -- --ignored --nocapture`: the examples, a thousand times
+/// over, checked the way `--check` did before and does now.
+#[test]
+#[ignore]
+fn benchmark_slim_syntax_checks() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples");
+    let sources: Vec<Vec<u8>> = std::fs::read_dir(examples).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .map(|path| std::fs::read(path).unwrap())
+        .collect();
+
+    let time = |check: &dyn Fn(&[u8]) -> bool| {
+        let started = std::time::Instant::now();
+        let accepted = (0..1000).map(|_| sources.iter().filter(|source| check(source)).count()).sum::<usize>();
+        (started.elapsed(), accepted)
+    };
+    let (full, full_accepted) = time(&|source| tokenize(source).is_ok_and(|tokens| parse(&tokens, DEFAULT_MAX_DEPTH).is_ok()));
+    let (slim, slim_accepted) = time(&|source| tokenize_slim(source, Default::default()).is_ok_and(|tokens| parse_slim(&tokens, Options::default()).is_ok()));
+
+    println!("full: {:?}, slim: {:?}, {:.2}x", full, slim, full.as_secs_f64() / slim.as_secs_f64());
+    assert_eq!(slim_accepted, full_accepted);
+}
+
+#[test]
+fn deep_nesting_is_an_error_not_a_crash() {
+    let program = format!("x := {}1{}\n", "(".repeat(100_000), ")".repeat(100_000));
+    let tokens = tokenize(program.as_bytes()).unwrap();
+
+    let error = parse(&tokens, DEFAULT_MAX_DEPTH).unwrap_err();
+    assert!(matches!(error, Error::NestingTooDeep(_)), "{}", error);
+    assert_eq!(error.token_info().lexeme, "(");
+    assert!(matches!(eval::parse(&tokens, &mut HashMap::new()).unwrap_err(), eval::Error::ExpressionTooDeep(_)));
+}
+
+fn outcome(program: &str) -> ParseOutcome {
+    classify(&tokenize(program.as_bytes()).unwrap())
+}
+
+#[test]
+fn unfinished_input_is_incomplete() {
+    for program in ["x := (1 +", "x := (1 +\n", "for (i := 1 to 3) begin\n    CONSOLE i;\n", "while 1 {"] {
+        assert!(matches!(outcome(program), ParseOutcome::Incomplete), "{}", program);
+    }
+}
+
+#[test]
+fn wrong_input_is_an_error() {
+    for program in ["x := )", "x := )\n", "x := 1 2", "for 5 begin"] {
+        assert!(matches!(outcome(program), ParseOutcome::Error(_)), "{}", program);
+    }
+
+    assert!(matches!(outcome("x := (1 + 2)"), ParseOutcome::Complete));
+}
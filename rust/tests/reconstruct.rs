@@ -0,0 +1,33 @@
+use rust::tokenizer::{reconstruct, tokenize, tokenize_with_options, Options};
+use std::fs;
+
+fn round_trip(source: &str) -> String {
+    let options = Options { keep_trivia: true, ..Default::default() };
+    reconstruct(&tokenize_with_options(source.as_bytes(), options).unwrap())
+}
+
+#[test]
+fn example_programs_round_trip() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples");
+    for entry in fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "txt") {
+            let source = fs::read_to_string(&path).unwrap();
+            assert_eq!(round_trip(&source), source, "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn trailing_and_missing_whitespace_round_trip() {
+    for source in ["a := 1;  \n\tCONSOLE a  \n\n", "a:=1;CONSOLE a", "  \n", "", "for (i := 1 to 2)\tbegin\n  i\nend   "] {
+        assert_eq!(round_trip(source), source);
+    }
+}
+
+#[test]
+fn trivia_is_only_kept_when_asked_for() {
+    let tokens = tokenize("a  :=  1\n".as_bytes()).unwrap();
+    assert!(tokens.iter().all(|token_info| token_info.leading_trivia.is_empty()));
+    assert_eq!(reconstruct(&tokens), "a:=1");
+}
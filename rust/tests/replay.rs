@@ -0,0 +1,55 @@
+use rust::tokenizer::{self, read_replay, write_replay, Options, ReplayError};
+use rust::{eval, parser};
+use std::collections::HashMap;
+use std::fs;
+
+fn replayed(tokens: &[tokenizer::TokenInfo]) -> Vec<tokenizer::TokenInfo> {
+    let mut dump = Vec::new();
+    write_replay(&mut dump, tokens).unwrap();
+    read_replay(dump.as_slice()).unwrap()
+}
+
+#[test]
+fn examples_evaluate_identically_after_replay() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples");
+    for entry in fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "txt") {
+            continue;
+        }
+
+        let tokens = tokenizer::tokenize(fs::read(&path).unwrap().as_slice()).unwrap();
+        let reloaded = replayed(&tokens);
+        assert_eq!(reloaded, tokens, "{}", path.display());
+        assert_eq!(parser::parse(&reloaded, parser::DEFAULT_MAX_DEPTH).is_ok(), parser::parse(&tokens, parser::DEFAULT_MAX_DEPTH).is_ok());
+        assert_eq!(
+            eval::parse(&reloaded, &mut HashMap::new()).map_err(|error| error.to_string()),
+            eval::parse(&tokens, &mut HashMap::new()).map_err(|error| error.to_string())
+        );
+    }
+}
+
+#[test]
+fn unusual_lexemes_are_escaped() {
+    let options = Options { recover_invalid_characters: true, ..Default::default() };
+    let tokens = tokenizer::tokenize_with_options(b"a \" \\ \x01 \xe9 \r b".as_slice(), options).unwrap();
+    assert_eq!(replayed(&tokens), tokens);
+}
+
+#[test]
+fn malformed_lines_are_reported_with_their_line_number() {
+    let dump = "tokens v1\n1 1 IDENTIFIER \"a\"\n1 3 SPACESHIP \":=\"\n1 6 EOF \"\"\n";
+    match read_replay(dump.as_bytes()) {
+        Err(ReplayError::Malformed(line, reason)) => {
+            assert_eq!(line, 3);
+            assert_eq!(reason, "unknown token 'SPACESHIP'");
+        },
+        other => panic!("unexpected {:?}", other)
+    }
+}
+
+#[test]
+fn other_versions_and_truncated_streams_are_rejected() {
+    assert!(matches!(read_replay("tokens v0\n1 1 EOF \"\"\n".as_bytes()), Err(ReplayError::UnsupportedVersion(1, _))));
+    assert!(matches!(read_replay("tokens v1\n1 1 INT \"1\"\n".as_bytes()), Err(ReplayError::MissingEOF(2))));
+}
@@ -0,0 +1,55 @@
+use rust::tokenizer::{retokenize, tokenize_with_options, Options, TextEdit};
+
+const FRAGMENTS: [&str; 16] = ["", " ", "\n", "\n\n", "a", "x1", "12", "#F", ":=", ";", "(", "}", "for", "BEGIN", "@", "  CONSOLE x;\n"];
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+fn random_edit(rng: &mut XorShift, src: &str) -> (TextEdit, String) {
+    let start = rng.below(src.len() + 1);
+    let end = (start + rng.below(8)).min(src.len());
+    let new_text = FRAGMENTS[rng.below(FRAGMENTS.len())].to_string();
+    let new_src = format!("{}{}{}", &src[..start], new_text, &src[end..]);
+    (TextEdit { range: start..end, new_text }, new_src)
+}
+
+#[test]
+fn random_edits_match_a_full_tokenize() {
+    let options = [
+        Options::default(),
+        Options { keep_trivia: true, ..Options::default() },
+        Options { case_insensitive_keywords: true, recover_invalid_characters: true, keep_trivia: true, ..Options::default() }
+    ];
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples");
+    let mut rng = XorShift(0x5eed_0003);
+    for entry in std::fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "txt") {
+            continue;
+        }
+
+        let mut src = std::fs::read_to_string(&path).unwrap();
+        for options in options {
+            for _ in 0..50 {
+                let Ok(tokens) = tokenize_with_options(src.as_bytes(), options) else { break };
+                let (edit, new_src) = random_edit(&mut rng, &src);
+                let full = tokenize_with_options(new_src.as_bytes(), options);
+                let incremental = retokenize(&tokens, &src, edit.clone(), &new_src, options);
+                match (full, incremental) {
+                    (Ok(full), Ok(incremental)) => assert_eq!(incremental, full, "{:?} on {:?} with {:?}", edit, src, options),
+                    (Err(_), Err(_)) => continue,
+                    (full, incremental) => panic!("{:?} on {:?}: full {:?}, incremental {:?}", edit, src, full.is_ok(), incremental.is_ok())
+                }
+                src = new_src;
+            }
+        }
+    }
+}
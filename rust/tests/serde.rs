@@ -0,0 +1,22 @@
+#![cfg(feature = "serde")]
+
+use rust::tokenizer::{tokenize, Token, TokenInfo};
+
+#[test]
+fn token_stream_round_trips_through_json() {
+    let tokens = tokenize("x := #FF + 1;\nfor (i := 0 to x) begin CONSOLE i end\n".as_bytes()).unwrap();
+    let json = serde_json::to_string(&tokens).unwrap();
+    let parsed: Vec<TokenInfo> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed, tokens);
+}
+
+#[test]
+fn every_token_variant_round_trips() {
+    for i in 0..32 {
+        let token = Token::from(i);
+        let json = serde_json::to_string(&token).unwrap();
+        assert_eq!(serde_json::from_str::<Token>(&json).unwrap(), token);
+        assert_eq!(json, format!("\"{:?}\"", token));
+    }
+}
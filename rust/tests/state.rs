@@ -0,0 +1,57 @@
+use rust::eval::{ForBoundMode, Interpreter, Options, StateError};
+use rust::tokenizer::tokenize;
+
+#[test]
+fn empty_interpreters_round_trip() {
+    let state = Interpreter::default().save_state();
+    assert!(state.starts_with("interpreter v1\n"));
+
+    let restored = Interpreter::restore_state(&state).unwrap();
+    assert!(restored.variables.is_empty());
+    assert_eq!(restored.save_state(), state);
+}
+
+#[test]
+fn many_variables_and_options_round_trip() {
+    let mut interpreter = Interpreter::new(Options { group_digits: true, max_steps: Some(500), for_bound: ForBoundMode::Reevaluated, coverage: true, ..Default::default() });
+    for i in 0..10_000 {
+        interpreter.variables.insert(format!("v{}", i), i64::MAX - i);
+    }
+    // names can't be written like this in a program, but the map accepts anything
+    interpreter.variables.insert(String::from("odd \"name\"\n\\ é"), i64::MIN);
+
+    let restored = Interpreter::restore_state(&interpreter.save_state()).unwrap();
+    assert_eq!(restored.variables, interpreter.variables);
+    assert_eq!(restored.options.max_steps, Some(500));
+    assert_eq!(restored.options.for_bound, ForBoundMode::Reevaluated);
+    assert!(restored.options.group_digits);
+    assert!(restored.options.coverage);
+}
+
+#[test]
+fn states_saved_mid_program_resume_with_the_next_statement() {
+    let tokens = tokenize("a := 1;\nCONSOLE a;\nfor (i := 1 to 3) { a := a * 2 };\nCONSOLE a;\nb := a + 5\n".as_bytes()).unwrap();
+    let mut interpreter = Interpreter::default();
+    let mut statements = interpreter.run_iter(&tokens);
+    statements.next().unwrap().unwrap();
+    statements.next().unwrap().unwrap();
+    statements.next().unwrap().unwrap();
+    let state = statements.save_state();
+
+    let mut restored = Interpreter::restore_state(&state).unwrap();
+    let rest: Vec<_> = restored.run_iter(&tokens).map(Result::unwrap).collect();
+    assert_eq!(rest.iter().map(|outcome| outcome.line).collect::<Vec<_>>(), [4, 5]);
+    assert_eq!(rest[0].console_output, "8\n");
+    assert_eq!(restored.variables["b"], 13);
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let state = Interpreter::default().save_state().replacen("v1", "v2", 1);
+    let error = Interpreter::restore_state(&state).unwrap_err();
+    assert!(matches!(error, StateError::UnsupportedVersion(_)));
+    assert_eq!(error.to_string(), "State error: expected header 'interpreter v1', found 'interpreter v2' on line 1");
+
+    let error = Interpreter::restore_state("interpreter v1\nvar \"a\" lots\n").unwrap_err();
+    assert_eq!(error.to_string(), "State error: invalid variable value 'lots' on line 2");
+}
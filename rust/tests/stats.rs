@@ -0,0 +1,19 @@
+use rust::stats::stats;
+use rust::tokenizer::{tokenize, Token};
+
+#[test]
+fn counts_a_known_program() {
+    let program = "sum := 0;\nfor (i := 1 to 5) begin\n    sum := sum + (i * i)\nend;\nCONSOLE sum\n";
+    let stats = stats(&tokenize(program.as_bytes()).unwrap());
+
+    assert_eq!(stats.statements, 3);
+    assert_eq!(stats.max_nesting, 2);
+    assert_eq!(stats.distinct_identifiers, 2);
+    assert_eq!(stats.lines, 5);
+    assert_eq!(stats.counts[&Token::Identifier], 7);
+    assert_eq!(stats.counts[&Token::Assignment], 3);
+    assert_eq!(stats.counts[&Token::Semicolon], 2);
+    assert_eq!(stats.counts[&Token::LeftParantheses], 2);
+    assert_eq!(stats.counts[&Token::Int], 3);
+    assert_eq!(stats.counts.get(&Token::EOF), None);
+}
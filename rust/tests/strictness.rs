@@ -0,0 +1,67 @@
+use rust::tokenizer::{self, Strictness};
+use rust::{parser, eval};
+use std::collections::HashMap;
+
+/// Runs `program` with every lenient option turned on, so only `strictness` decides what's accepted.
+fn run(program: &str, strictness: Strictness) -> Result<HashMap<String, i64>, Box<dyn std::error::Error>> {
+    let tokenizer_options = tokenizer::Options { case_insensitive_keywords: true, strictness, ..Default::default() };
+    let tokens = tokenizer::tokenize_with_options(program.as_bytes(), tokenizer_options)?;
+    parser::parse_with_options(&tokens, parser::Options { permissive_console: true, strictness, ..Default::default() })?;
+    let mut variables = HashMap::new();
+    eval::parse_with_options(&tokens, &mut variables, eval::Options { permissive_console: true, strictness, ..Default::default() })?;
+    Ok(variables)
+}
+
+#[test]
+fn corpus_under_both_modes() {
+    // (program, accepted as Legacy, accepted as Strict)
+    let corpus = [
+        ("a := 1;\nCONSOLE a + 1\n", true, true),
+        ("a := 1 + CONSOLE 2\n", true, false),
+        ("a := 1;\nCONSOLE a := 5\n", true, false),
+        ("s := 0;\nFor (i := 1 to 3) begin s := s + i end\n", true, false),
+        ("s := 0;\nfor (i := 1 to 3) begin s := s + i end;\nb := i\n", true, false),
+        ("i := 7;\nfor (i := 1 to 3) begin a := i end;\nb := i\n", true, true),
+        ("a := 1 @ 2\n", false, false),
+        ("c := (d := 5) + 1\n", true, false),
+        ("a := b := 5\n", true, false),
+        ("x := { y := 5; y + 1 }\n", true, true),
+        ("1 + 1;\na := 2\n", true, false),
+        ("CONSOLE { x := 5 }\n", true, false)
+    ];
+
+    for (program, legacy, strict) in corpus {
+        assert_eq!(run(program, Strictness::Legacy).is_ok(), legacy, "{}", program);
+        assert_eq!(run(program, Strictness::Strict).is_ok(), strict, "{}", program);
+    }
+}
+
+#[test]
+fn strict_loops_restore_the_control_variable() {
+    let program = "i := 7;\nfor (i := 1 to 3) begin a := i end;\nb := i\n";
+
+    assert_eq!(run(program, Strictness::Legacy).unwrap()["b"], 3);
+    let variables = run(program, Strictness::Strict).unwrap();
+    assert_eq!((variables["a"], variables["b"]), (3, 7));
+}
+
+#[test]
+fn strict_tokenizing_ignores_recovery() {
+    let options = tokenizer::Options { recover_invalid_characters: true, strictness: Strictness::Strict, ..Default::default() };
+    assert!(tokenizer::tokenize_with_options("a := 1 @ 2\n".as_bytes(), options).is_err());
+}
+
+#[test]
+fn strict_mode_names_the_nested_assignment_and_the_unused_value() {
+    let error = run("c := (d := 5) + 1\n", Strictness::Strict).unwrap_err();
+    assert_eq!(error.to_string(), "Syntax error: invalid assignment; found ':=' of type ASSIGNMENT after \"c := ( d :=\" on line 1");
+
+    // eval checks the same on tokens that never went through the parser
+    let tokens = tokenizer::tokenize("c := (d := 5) + 1\n".as_bytes()).unwrap();
+    let options = eval::Options { strictness: Strictness::Strict, ..Default::default() };
+    assert!(matches!(eval::parse_with_options(&tokens, &mut HashMap::new(), options), Err(eval::Error::InvalidAssignment(..))));
+
+    // the parser has nothing against an unused value, so this comes from eval
+    let error = run("1 + 1;\na := 2\n", Strictness::Strict).unwrap_err();
+    assert!(error.to_string().starts_with("Evaluation error: value of the statement starting with '1'"), "{}", error);
+}
@@ -0,0 +1,117 @@
+use rust::tokenizer::{tokenize, tokenize_with_options, trace, Error, Options, Position, Strictness, Token, TokenInfo};
+
+fn lexemes(source: &str) -> Vec<(Token, String)> {
+    tokenize(source.as_bytes()).unwrap().into_iter().map(|token_info| (token_info.token, token_info.lexeme)).collect()
+}
+
+#[test]
+fn identifiers_stop_at_punctuation() {
+    assert_eq!(lexemes("abc1;b2)"), [
+        (Token::Identifier, String::from("abc1")),
+        (Token::Semicolon, String::from(";")),
+        (Token::Identifier, String::from("b2")),
+        (Token::RightParantheses, String::from(")")),
+        (Token::EOF, String::new())
+    ]);
+}
+
+#[test]
+fn identifiers_stop_at_the_end_of_input() {
+    for source in ["total", "total\n", "total \t\n"] {
+        assert_eq!(lexemes(source), [(Token::Identifier, String::from("total")), (Token::EOF, String::new())], "{:?}", source);
+    }
+}
+
+#[test]
+fn keyword_case_only_matters_without_case_insensitive_keywords() {
+    // (source, the keyword it spells, whether it's spelled exactly)
+    let cases = [
+        ("begin", Token::Begin, true), ("Begin", Token::Begin, false), ("BEGIN", Token::Begin, false),
+        ("for", Token::For, true), ("For", Token::For, false),
+        ("CONSOLE", Token::Console, true), ("console", Token::Console, false), ("Console", Token::Console, false)
+    ];
+    for (source, keyword, exact) in cases {
+        let token = |case_insensitive_keywords, strictness| {
+            let options = Options { case_insensitive_keywords, strictness, ..Options::default() };
+            tokenize_with_options(source.as_bytes(), options).unwrap()[0].token
+        };
+        let case_sensitive = if exact { keyword } else { Token::Identifier };
+        assert_eq!(token(false, Strictness::Legacy), case_sensitive, "{}", source);
+        assert_eq!(token(true, Strictness::Legacy), keyword, "{}", source);
+        // Strict ignores case_insensitive_keywords
+        assert_eq!(token(true, Strictness::Strict), case_sensitive, "{}", source);
+    }
+}
+
+#[test]
+fn trace_follows_the_dfa() {
+    assert_eq!(trace("12+3"), [('1', Token::Int), ('2', Token::Int), ('+', Token::Addition), ('3', Token::Int)]);
+}
+
+#[test]
+fn trace_shows_whitespace_and_raw_states() {
+    // reserved words are looked up once the DFA has accepted an identifier
+    assert_eq!(trace("to x"), [('t', Token::Identifier), ('o', Token::Identifier), (' ', Token::EOT), ('x', Token::Identifier)]);
+}
+
+#[test]
+fn whitespace_only_input_is_just_eof() {
+    // EOF sits just past the last character
+    let cases = [("", (1, 1)), (" ", (1, 2)), ("\n\n", (3, 1)), (" \t \n  ", (2, 3))];
+    for (source, (row, col)) in cases {
+        let tokens = tokenize_with_options(source.as_bytes(), Options { keep_trivia: true, ..Options::default() }).unwrap();
+        assert_eq!(tokens.len(), 1, "{:?}", source);
+        assert_eq!((tokens[0].token, tokens[0].leading_trivia.as_str()), (Token::EOF, source));
+        assert_eq!(tokens[0].start_position, Position { row, col }, "{:?}", source);
+        assert_eq!(tokenize(source.as_bytes()).unwrap(), [TokenInfo { leading_trivia: String::new(), ..tokens[0].clone() }]);
+    }
+}
+
+#[test]
+fn there_are_no_comments() {
+    // `#` starts a hex literal, so a would-be comment line is code like any other
+    let tokens: Vec<Token> = lexemes("# only a comment\n").into_iter().map(|(token, _)| token).collect();
+    assert_eq!(tokens, [Token::Hex, Token::Identifier, Token::Identifier, Token::Identifier, Token::EOF]);
+}
+
+#[test]
+fn nul_bytes_are_invalid() {
+    assert!(matches!(tokenize("a\0".as_bytes()), Err(Error::InvalidPattern(lexeme, Position { row: 1, col: 2 })) if lexeme == "\0"));
+    assert!(tokenize("\0".as_bytes()).is_err());
+}
+
+#[test]
+fn unit_separator_bytes_are_invalid_rather_than_the_end_of_input() {
+    // 0x1F is Token::EOF's discriminant, which used to end the token like whitespace
+    assert!(matches!(tokenize("a\x1fb".as_bytes()), Err(Error::InvalidPattern(lexeme, Position { row: 1, col: 2 })) if lexeme == "\x1f"));
+    assert!(tokenize("\x1f".as_bytes()).is_err());
+}
+
+#[test]
+fn the_last_token_is_kept_without_a_trailing_newline() {
+    assert_eq!(lexemes("x := )"), [
+        (Token::Identifier, String::from("x")),
+        (Token::Assignment, String::from(":=")),
+        (Token::RightParantheses, String::from(")")),
+        (Token::EOF, String::new())
+    ]);
+    assert_eq!(lexemes("CONSOLE 12").len(), 3);
+}
+
+#[test]
+fn positions_are_at_the_first_character() {
+    let positions: Vec<(u32, u32)> = tokenize("ab := 12;\n  CONSOLE ab\n".as_bytes()).unwrap().iter()
+        .map(|token_info| (token_info.start_position.row, token_info.start_position.col))
+        .collect();
+    assert_eq!(positions, [(1, 1), (1, 4), (1, 7), (1, 9), (2, 3), (2, 11), (3, 1)]);
+}
+
+#[test]
+fn recovery_mode_turns_unknown_characters_into_error_tokens() {
+    let options = Options { recover_invalid_characters: true, ..Options::default() };
+    let tokens = tokenize_with_options("1 @ 2".as_bytes(), options).unwrap();
+    let kinds: Vec<(Token, &str, u32)> = tokens.iter().map(|token_info| (token_info.token, token_info.lexeme.as_str(), token_info.start_position.col)).collect();
+    assert_eq!(kinds, [(Token::Int, "1", 1), (Token::Error, "@", 3), (Token::Int, "2", 5), (Token::EOF, "", 6)]);
+
+    assert!(matches!(tokenize("1 @ 2".as_bytes()), Err(Error::InvalidPattern(lexeme, Position { row: 1, col: 3 })) if lexeme == "@"));
+}